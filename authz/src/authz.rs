@@ -1,81 +1,432 @@
 use cedar_policy::{
 	Authorizer, Context, Entities, EntitiesError, EntityUid, ParseErrors, PolicySet, Request,
-	Response,
+	Response, Schema,
 };
 use jwt::JWTDecoder;
 
+mod claim_mapping;
+mod entity_shapes;
 mod jwt_data_handler;
-pub use jwt_data_handler::AuthzInputRaw;
-use jwt_data_handler::{AuthzInputEntitiesError, DecodeTokensError, JWTData};
+pub use jwt_data_handler::{
+	AuthzInputEntitiesError, AuthzInputRaw, DecodeTokensError, TokenKind, TokensConsistencyConfig,
+	TrustedIssuerError, UntrustedTokenMode,
+};
+use jwt_data_handler::{EntitiesConfig, JWTData};
 pub(crate) mod jwt_tokens;
 mod policy_store;
-use policy_store::{PolicyStoreEntry, TrustedIssuers};
+mod principal;
+mod remote_store;
+mod source;
+mod store_entities;
+mod validation;
+mod watcher;
+pub use policy_store::DEFAULT_POLICY_STORE_ID;
+use policy_store::{parse_policy_stores, PolicyStoreEntry, PolicyStores};
+use principal::PrincipalKind;
+pub use principal::{AuthzResponse, DecisionRule, PrincipalMode};
+use remote_store::{RemoteDocument, RemotePolicy};
+pub use remote_store::{RemoteStoreError, RemoteTransport};
+pub use store_entities::EntityConflictMode;
+pub use validation::{PolicyIssue, PolicyValidationError, ValidationWarningMode};
+pub use watcher::PolicyStoreWatcher;
 
 pub(crate) mod exp_parsers;
 
-use std::str::FromStr;
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::{Arc, PoisonError, RwLock},
+	time::SystemTime,
+};
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(tag = "strategy")]
 #[serde(rename_all = "kebab-case")]
 #[serde(rename_all_fields = "camelCase")]
 pub enum PolicyStoreConfig {
+	// policy store compiled into the binary
 	Local,
+	// policy store json file read at runtime
+	File {
+		path: PathBuf,
+	},
+	// policy store json document,
+	// the document contains single policy store or several ones: `{ "policyStores": { "<id>": .. } }`
+	Json {
+		value: String,
+	},
+	// policy store downloaded by url, the last downloaded copy is kept in `cache_path`
+	Remote {
+		url: String,
+		cache_path: PathBuf,
+		#[serde(skip)]
+		transport: RemoteTransport,
+	},
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum GetPolicyError {
 	#[error("could not parse policy form json: {0}")]
 	ParseJson(#[from] serde_json::Error),
+	#[error("could not read policy store file {path:?}: {source}")]
+	ReadFile {
+		path: PathBuf,
+		source: std::io::Error,
+	},
+	#[error("could not get remote policy store: {0}")]
+	Remote(#[from] RemoteStoreError),
+}
+
+// version of the loaded policy store, used to find out if it is changed
+#[derive(Debug, Clone, PartialEq)]
+enum StoreVersion {
+	// policy store can not be changed
+	Static,
+	// modification time of the policy store file
+	Modified(Option<SystemTime>),
+	// ETag of the remote policy store
+	ETag(Option<String>),
+}
+
+// policy stores got from the source and not checked yet
+struct LoadedPolicy {
+	stores: PolicyStores,
+	version: StoreVersion,
+	// downloaded remote document, it is cached only when the policy stores are accepted
+	document: Option<RemoteDocument>,
+}
+
+impl LoadedPolicy {
+	fn new(stores: PolicyStores, version: StoreVersion) -> Self {
+		Self {
+			stores,
+			version,
+			document: None,
+		}
+	}
+
+	fn remote(remote: RemotePolicy) -> Self {
+		Self {
+			stores: remote.stores,
+			version: StoreVersion::ETag(remote.etag),
+			document: remote.document,
+		}
+	}
 }
 
 impl PolicyStoreConfig {
-	fn get_policy(self) -> Result<PolicyStoreEntry, GetPolicyError> {
+	fn get_policy(&self) -> Result<LoadedPolicy, GetPolicyError> {
+		match self {
+			Self::Local => Ok(LoadedPolicy::new(
+				Self::get_local_policy()?,
+				StoreVersion::Static,
+			)),
+			Self::File { path } => Self::get_file_policy(path),
+			Self::Json { value } => Ok(LoadedPolicy::new(
+				parse_policy_stores(value)?,
+				StoreVersion::Static,
+			)),
+			Self::Remote {
+				url,
+				cache_path,
+				transport,
+			} => {
+				let remote =
+					remote_store::get_remote_policy(url, cache_path, transport.0.as_ref())?;
+				Ok(LoadedPolicy::remote(remote))
+			}
+		}
+	}
+
+	// get policy store only if it differs from the loaded `version`
+	fn get_changed_policy(
+		&self,
+		version: &StoreVersion,
+	) -> Result<Option<LoadedPolicy>, GetPolicyError> {
 		match self {
-			Self::Local => Self::get_local_policy(),
+			Self::Local | Self::Json { .. } => Ok(None),
+			Self::File { path } => {
+				if StoreVersion::Modified(Self::modified(path)) == *version {
+					return Ok(None);
+				}
+				Self::get_file_policy(path).map(Some)
+			}
+			Self::Remote { url, transport, .. } => {
+				let etag = match version {
+					StoreVersion::ETag(etag) => etag.as_deref(),
+					_ => None,
+				};
+				let remote = remote_store::fetch_remote_policy(url, transport.0.as_ref(), etag)?;
+				Ok(remote.map(LoadedPolicy::remote))
+			}
 		}
 	}
 
-	fn get_local_policy() -> Result<PolicyStoreEntry, GetPolicyError> {
+	// the last accepted copy of the remote policy store
+	fn get_cached_policy(&self) -> Option<LoadedPolicy> {
+		match self {
+			Self::Remote { cache_path, .. } => {
+				remote_store::cached_remote_policy(cache_path).map(LoadedPolicy::remote)
+			}
+			_ => None,
+		}
+	}
+
+	// downloaded document is cached only after its policy stores are accepted,
+	// so an invalid remote policy store does not replace the cached copy
+	fn cache_document(&self, document: Option<&RemoteDocument>) {
+		if let (Self::Remote { cache_path, .. }, Some(document)) = (self, document) {
+			document.cache(cache_path);
+		}
+	}
+
+	fn get_local_policy() -> Result<PolicyStores, GetPolicyError> {
 		let policy_raw = include_str!("../../policy-store/local.json");
-		let policy: PolicyStoreEntry = serde_json::from_str(policy_raw)?;
-		Ok(policy)
+		Ok(parse_policy_stores(policy_raw)?)
+	}
+
+	fn get_file_policy(path: &Path) -> Result<LoadedPolicy, GetPolicyError> {
+		// modification time is taken before reading, so a change made meanwhile is not missed
+		let version = StoreVersion::Modified(Self::modified(path));
+		let policy_raw =
+			std::fs::read_to_string(path).map_err(|source| GetPolicyError::ReadFile {
+				path: path.to_owned(),
+				source,
+			})?;
+		Ok(LoadedPolicy::new(
+			parse_policy_stores(&policy_raw)?,
+			version,
+		))
+	}
+
+	fn modified(path: &Path) -> Option<SystemTime> {
+		std::fs::metadata(path).and_then(|m| m.modified()).ok()
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SelectPolicyStoreError {
+	#[error("policy store {0:?} is not found")]
+	NotFound(String),
+	#[error("policy store id is not set, but the document contains {0} policy stores")]
+	NotSelected(usize),
+}
+
+// policy stores in use, they are replaced as a whole on reload
+struct PolicyStoreSnapshot {
+	stores: PolicyStores,
+	version: StoreVersion,
+}
+
+// find policy store by id, the id can be omitted if there is only one policy store
+fn select_store<'a>(
+	stores: &'a PolicyStores,
+	id: Option<&str>,
+) -> Result<&'a PolicyStoreEntry, SelectPolicyStoreError> {
+	match id {
+		Some(id) => stores
+			.get(id)
+			.ok_or_else(|| SelectPolicyStoreError::NotFound(id.to_owned())),
+		None if stores.len() == 1 => Ok(stores.values().next().expect("checked length")),
+		None => Err(SelectPolicyStoreError::NotSelected(stores.len())),
 	}
 }
 
+// application served by the instance and its policy store
+struct Application {
+	name: Option<String>,
+	policy_store_id: Option<String>,
+}
+
 pub struct Authz {
-	app_name: Option<String>,
+	default_app: Application,
+	applications: BTreeMap<String, Application>,
 	jwt_dec: JWTDecoder,
-	policy: PolicySet,
-	schema: cedar_policy::Schema,
-	trusted_issuers: TrustedIssuers,
+	consistency: TokensConsistencyConfig,
+	untrusted_tokens: UntrustedTokenMode,
+	entity_conflicts: EntityConflictMode,
+	validation_warnings: ValidationWarningMode,
+	principal_mode: PrincipalMode,
+	policy_config: PolicyStoreConfig,
+	store: RwLock<Arc<PolicyStoreSnapshot>>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum AuthzNewError {
 	#[error("could not get policy store: {0}")]
 	PolicyStore(#[from] GetPolicyError),
+	#[error("could not select policy store of the application {app:?}: {source}")]
+	SelectPolicyStore {
+		app: Option<String>,
+		source: SelectPolicyStoreError,
+	},
+	#[error("{0}")]
+	Validation(#[from] PolicyValidationError),
 	#[error("could not parse entities: {0}")]
-	Entities(#[from] EntitiesError),
+	Entities(Box<EntitiesError>),
 }
 
 pub struct AuthzConfig {
 	pub app_name: Option<String>,
 	pub decoder: JWTDecoder,
 	pub policy: PolicyStoreConfig,
+	// id of the policy store used by `app_name`,
+	// can be omitted if the policy store document contains only one policy store
+	pub policy_store_id: Option<String>,
+	// other applications served by the instance: policy store id keyed by application name
+	pub applications: BTreeMap<String, String>,
+	// checks of claims between id_token, userinfo_token and access_token
+	pub consistency: TokensConsistencyConfig,
+	// how to handle tokens which kind is not trusted by the issuer in the policy store
+	pub untrusted_tokens: UntrustedTokenMode,
+	// how to handle entity created from the tokens with the same uid as entity of the policy store
+	pub entity_conflicts: EntityConflictMode,
+	// policies are validated against the schema on load, validation errors are always fatal
+	pub validation_warnings: ValidationWarningMode,
+	// principals authorized by the request: the user, the workload of the access_token or both
+	pub principal_mode: PrincipalMode,
+}
+
+impl AuthzConfig {
+	/// Config of the application `app_name` with default options,
+	/// they can be changed by the fields before the config is used.
+	pub fn new(app_name: Option<String>, decoder: JWTDecoder, policy: PolicyStoreConfig) -> Self {
+		Self {
+			app_name,
+			decoder,
+			policy,
+			policy_store_id: None,
+			applications: BTreeMap::new(),
+			consistency: TokensConsistencyConfig::default(),
+			untrusted_tokens: UntrustedTokenMode::default(),
+			entity_conflicts: EntityConflictMode::default(),
+			validation_warnings: ValidationWarningMode::default(),
+			principal_mode: PrincipalMode::default(),
+		}
+	}
 }
 
 impl Authz {
 	pub fn new(config: AuthzConfig) -> Result<Authz, AuthzNewError> {
-		let policy_store = config.policy.get_policy()?;
+		let policy = config.policy.get_policy()?;
+		let downloaded = policy.document.is_some();
+
+		let applications = config
+			.applications
+			.into_iter()
+			.map(|(name, policy_store_id)| {
+				let app = Application {
+					name: Some(name.clone()),
+					policy_store_id: Some(policy_store_id),
+				};
+				(name, app)
+			})
+			.collect();
 
-		Ok(Authz {
-			app_name: config.app_name,
+		let authz = Authz {
+			default_app: Application {
+				name: config.app_name,
+				policy_store_id: config.policy_store_id,
+			},
+			applications,
 			jwt_dec: config.decoder,
-			policy: policy_store.policies,
-			schema: policy_store.schema,
-			trusted_issuers: policy_store.trusted_issuers,
-		})
+			consistency: config.consistency,
+			untrusted_tokens: config.untrusted_tokens,
+			entity_conflicts: config.entity_conflicts,
+			validation_warnings: config.validation_warnings,
+			principal_mode: config.principal_mode,
+			policy_config: config.policy,
+			// replaced by the loaded policy stores once they are checked
+			store: RwLock::new(Arc::new(PolicyStoreSnapshot {
+				stores: PolicyStores::new(),
+				version: StoreVersion::Static,
+			})),
+		};
+
+		if let Err(err) = authz.set_store(policy) {
+			// rejected remote policy store is replaced by the last accepted copy of the cache
+			let cached = downloaded
+				.then(|| authz.policy_config.get_cached_policy())
+				.flatten();
+			if cached.is_none_or(|cached| authz.set_store(cached).is_err()) {
+				return Err(err);
+			}
+			log::warn!("downloaded policy store is rejected, cached copy is used: {err}");
+		}
+		Ok(authz)
+	}
+
+	/// Load the policy store again and replace the one in use.
+	/// If the policy store could not be loaded the current one is kept.
+	///
+	/// Requests that are handled at the moment use the previous policy store.
+	pub fn reload(&self) -> Result<(), AuthzNewError> {
+		let policy = self.policy_config.get_policy()?;
+		self.set_store(policy)
+	}
+
+	/// Reload the policy store only if it is changed since it was loaded:
+	/// modification time of the policy store file or ETag of the remote policy store is changed.
+	/// Returns `true` if the policy store is replaced.
+	pub fn reload_if_changed(&self) -> Result<bool, AuthzNewError> {
+		let version = self.store().version.clone();
+		match self.policy_config.get_changed_policy(&version)? {
+			Some(policy) => {
+				self.set_store(policy)?;
+				Ok(true)
+			}
+			None => Ok(false),
+		}
+	}
+
+	fn store(&self) -> Arc<PolicyStoreSnapshot> {
+		// the snapshot is replaced by single assignment, so poisoned lock can be reused
+		self.store
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clone()
+	}
+
+	// every application should have its policy store and policies should be valid
+	fn check_stores(&self, stores: &PolicyStores) -> Result<(), AuthzNewError> {
+		let applications = std::iter::once(&self.default_app).chain(self.applications.values());
+		for app in applications {
+			select_store(stores, app.policy_store_id.as_deref()).map_err(|source| {
+				AuthzNewError::SelectPolicyStore {
+					app: app.name.clone(),
+					source,
+				}
+			})?;
+		}
+		validation::validate_stores(stores, self.validation_warnings)?;
+		Ok(())
+	}
+
+	fn set_store(&self, policy: LoadedPolicy) -> Result<(), AuthzNewError> {
+		let LoadedPolicy {
+			stores,
+			version,
+			document,
+		} = policy;
+		self.check_stores(&stores)?;
+		self.policy_config.cache_document(document.as_ref());
+		add_issuer_endpoints(&self.jwt_dec, &stores);
+		let snapshot = Arc::new(PolicyStoreSnapshot { stores, version });
+		*self.store.write().unwrap_or_else(PoisonError::into_inner) = snapshot;
+		Ok(())
+	}
+}
+
+// keys of trusted issuers are resolved by the decoder using their openid configuration
+fn add_issuer_endpoints(decoder: &JWTDecoder, stores: &PolicyStores) {
+	if let Some(resolver) = decoder.issuer_resolver() {
+		for issuer in stores
+			.values()
+			.flat_map(|store| store.trusted_issuers.values())
+		{
+			resolver.add_endpoint(&issuer.openid_configuration_endpoint);
+		}
 	}
 }
 
@@ -90,25 +441,60 @@ pub enum HandleError {
 	Action(ParseErrors),
 	#[error("could not parse resource from json: {0}")]
 	Resource(cedar_policy::ParseErrors),
+	#[error("token is not trusted: {0}")]
+	TrustedIssuer(#[from] TrustedIssuerError),
+	#[error("principal of the action {action} can not be created without {tokens}")]
+	MissingToken { action: String, tokens: String },
 	#[error("could not get entities from input: {0}")]
 	AuthzInputEntities(#[from] AuthzInputEntitiesError),
+	#[error("entity {0} of the tokens is already defined in the policy store")]
+	EntityConflict(String),
 	#[error("could not add entities values to entities list: {0}")]
-	AddEntities(#[from] EntitiesError),
+	AddEntities(Box<EntitiesError>),
 	#[error("could not create context: {0}")]
-	Context(cedar_policy::ContextJsonError),
+	Context(Box<cedar_policy::ContextJsonError>),
 	#[error("could not create request type: {0}")]
 	Request(String),
+	#[error("application {0:?} is not configured")]
+	UnknownApplication(String),
+	#[error("could not select policy store: {0}")]
+	SelectPolicyStore(#[from] SelectPolicyStoreError),
 }
 
 impl Authz {
-	pub fn handle_raw_input(&self, data: &str) -> Result<Response, HandleError> {
+	pub fn handle_raw_input(&self, data: &str) -> Result<AuthzResponse, HandleError> {
 		let input = jwt_data_handler::AuthzInputRaw::parse_raw(data)
 			.map_err(HandleError::InputJsonParse)?;
 
 		self.handle(input)
 	}
 
-	pub fn handle(&self, input: AuthzInputRaw) -> Result<Response, HandleError> {
+	pub fn handle(&self, input: AuthzInputRaw) -> Result<AuthzResponse, HandleError> {
+		self.handle_application(&self.default_app, input)
+	}
+
+	/// Handle request of the application from [`AuthzConfig::applications`]
+	/// using its policy store.
+	pub fn handle_app(
+		&self,
+		app_name: &str,
+		input: AuthzInputRaw,
+	) -> Result<AuthzResponse, HandleError> {
+		let app = self
+			.applications
+			.get(app_name)
+			.ok_or_else(|| HandleError::UnknownApplication(app_name.to_owned()))?;
+		self.handle_application(app, input)
+	}
+
+	fn handle_application(
+		&self,
+		app: &Application,
+		input: AuthzInputRaw,
+	) -> Result<AuthzResponse, HandleError> {
+		// the same policy store is used for the whole request even if it is reloaded meanwhile
+		let snapshot = self.store();
+		let store = select_store(&snapshot.stores, app.policy_store_id.as_deref())?;
 		let decoded_input = input.decode_tokens(&self.jwt_dec)?;
 		let params = decoded_input.chedar_params;
 		let action = EntityUid::from_str(params.action.as_str()).map_err(HandleError::Action)?;
@@ -118,43 +504,222 @@ impl Authz {
 			.entity_uid()
 			.map_err(HandleError::Resource)?;
 
-		let entities_box = self.get_entities(decoded_input.jwt)?;
+		let entities_box = self.store_entities(app, store, decoded_input.jwt)?;
 
-		let principal = entities_box.user_entity_uid;
+		let principals = entities_box.principals(self.principal_mode, &store.schema, &action)?;
 
-		let context = Context::from_json_value(params.context, Some((&self.schema, &action)))
-			.map_err(HandleError::Context)?;
+		let mut context = params.context;
+		// `tx_token` is only taken from the decoded token, the value given in the input is dropped,
+		// it is added only if the context of the action declares it
+		if let Some(attrs) = context.as_object_mut() {
+			attrs.remove("tx_token");
+			if let Some(uid) = entities_box
+				.tx_token_uid
+				.as_ref()
+				.filter(|_| store.entity_shapes.context_has_attr(&action, "tx_token"))
+			{
+				attrs.insert(
+					"tx_token".to_owned(),
+					serde_json::json!({ "__entity": { "type": uid.type_name().to_string(), "id": uid.id().as_ref() } }),
+				);
+			}
+		}
 
-		log::debug!("create cedar-policy request principal: {principal} action: {action} resource: {resource}");
+		let context = Context::from_json_value(context, Some((&store.schema, &action)))
+			.map_err(|err| HandleError::Context(Box::new(err)))?;
 
-		let request: Request = Request::new(
-			Some(principal),
-			Some(action),
-			Some(resource),
-			context,
-			Some(&self.schema),
-		)
-		.map_err(|err| HandleError::Request(err.to_string()))?;
+		let mut user = None;
+		let mut workload = None;
+		for (kind, principal) in principals {
+			let response = authorize(
+				principal,
+				&action,
+				&resource,
+				context.clone(),
+				&store.schema,
+				&store.policies,
+				&entities_box.entities,
+			)?;
+			match kind {
+				PrincipalKind::User => user = Some(response),
+				PrincipalKind::Workload => workload = Some(response),
+			}
+		}
 
-		let authorizer = Authorizer::new();
-		let decision = authorizer.is_authorized(&request, &self.policy, &entities_box.entities);
-		Ok(decision)
+		let rule = match self.principal_mode {
+			PrincipalMode::Combined(rule) => Some(rule),
+			_ => None,
+		};
+		Ok(AuthzResponse::new(rule, user, workload))
 	}
 
 	pub fn get_entities(&self, data: JWTData) -> Result<EntitiesBox, HandleError> {
-		// TODO: add entities from trust store about issuers (like in cedarling)
+		let snapshot = self.store();
+		let store = select_store(
+			&snapshot.stores,
+			self.default_app.policy_store_id.as_deref(),
+		)?;
+		self.store_entities(&self.default_app, store, data)
+	}
+
+	fn store_entities(
+		&self,
+		app: &Application,
+		store: &PolicyStoreEntry,
+		data: JWTData,
+	) -> Result<EntitiesBox, HandleError> {
+		let ignored_tokens =
+			data.check_trusted_issuers(&store.trusted_issuers, self.untrusted_tokens)?;
 
-		let jwt_entities = data.entities(self.app_name.as_deref())?;
+		let jwt_entities = data.entities(EntitiesConfig {
+			application_name: app.name.as_deref(),
+			consistency: &self.consistency,
+			trusted_issuers: &store.trusted_issuers,
+			entity_shapes: &store.entity_shapes,
+			ignored_tokens: &ignored_tokens,
+		})?;
 
-		let entities = Entities::empty().add_entities(jwt_entities.entities, Some(&self.schema))?;
+		let list = store_entities::merge_entities(
+			jwt_entities.entities,
+			&store.entities,
+			self.entity_conflicts,
+		)
+		.map_err(|uid| HandleError::EntityConflict(uid.to_string()))?;
+
+		let entities = Entities::empty()
+			.add_entities(list, Some(&store.schema))
+			.map_err(|err| HandleError::AddEntities(Box::new(err)))?;
 		Ok(EntitiesBox {
-			entities: entities,
+			entities,
 			user_entity_uid: jwt_entities.user_entity_uid,
+			client_entity_uid: jwt_entities.client_entity_uid,
+			application_entity_uid: jwt_entities.application_entity_uid,
+			tx_token_uid: jwt_entities.tx_token_uid,
 		})
 	}
 }
 
 pub struct EntitiesBox {
 	pub entities: Entities,
-	pub user_entity_uid: EntityUid,
+	// created if id_token or userinfo_token is given
+	pub user_entity_uid: Option<EntityUid>,
+	// created if access_token is given
+	pub client_entity_uid: Option<EntityUid>,
+	// created if access_token is given and the application name is configured
+	pub application_entity_uid: Option<EntityUid>,
+	// added to the request context as `tx_token`
+	pub tx_token_uid: Option<EntityUid>,
+}
+
+// authorize the request of single principal
+fn authorize(
+	principal: EntityUid,
+	action: &EntityUid,
+	resource: &EntityUid,
+	context: Context,
+	schema: &Schema,
+	policies: &PolicySet,
+	entities: &Entities,
+) -> Result<Response, HandleError> {
+	log::debug!(
+		"create cedar-policy request principal: {principal} action: {action} resource: {resource}"
+	);
+
+	let request: Request = Request::new(
+		Some(principal),
+		Some(action.clone()),
+		Some(resource.clone()),
+		context,
+		Some(schema),
+	)
+	.map_err(|err| HandleError::Request(err.to_string()))?;
+
+	let authorizer = Authorizer::new();
+	Ok(authorizer.is_authorized(&request, policies, entities))
+}
+
+// entity type, uid and tokens the entity is created from
+type Candidate<'a> = (
+	PrincipalKind,
+	&'static str,
+	&'a Option<EntityUid>,
+	&'static str,
+);
+
+impl EntitiesBox {
+	/// Principals of the request by the `mode`.
+	/// In [`PrincipalMode::Auto`] mode the user is used if the action accepts it and user tokens are given,
+	/// otherwise the workload of the access_token (client-credentials flow).
+	fn principals(
+		&self,
+		mode: PrincipalMode,
+		schema: &Schema,
+		action: &EntityUid,
+	) -> Result<Vec<(PrincipalKind, EntityUid)>, HandleError> {
+		let user: [Candidate; 1] = [(
+			PrincipalKind::User,
+			"Jans::User",
+			&self.user_entity_uid,
+			"id_token or userinfo_token",
+		)];
+		let workload: [Candidate; 2] = [
+			(
+				PrincipalKind::Workload,
+				"Jans::Client",
+				&self.client_entity_uid,
+				"access_token",
+			),
+			(
+				PrincipalKind::Workload,
+				"Jans::Application",
+				&self.application_entity_uid,
+				"access_token",
+			),
+		];
+
+		let principal = |candidates: &[Candidate]| select_principal(candidates, schema, action);
+		match mode {
+			PrincipalMode::Auto => Ok(vec![principal(&[user.as_slice(), &workload].concat())?]),
+			PrincipalMode::User => Ok(vec![principal(&user)?]),
+			PrincipalMode::Workload => Ok(vec![principal(&workload)?]),
+			PrincipalMode::Combined(_) => Ok(vec![principal(&user)?, principal(&workload)?]),
+		}
+	}
+}
+
+// the first created candidate which is accepted by the action
+fn select_principal(
+	candidates: &[Candidate],
+	schema: &Schema,
+	action: &EntityUid,
+) -> Result<(PrincipalKind, EntityUid), HandleError> {
+	let accepted_types: Option<Vec<String>> = schema
+		.principals_for_action(action)
+		.map(|types| types.map(ToString::to_string).collect());
+	let mut accepted: Vec<_> = candidates
+		.iter()
+		.filter(|(_, type_name, ..)| {
+			accepted_types
+				.as_ref()
+				.is_none_or(|types| types.iter().any(|t| t == type_name))
+		})
+		.collect();
+	// cedar reports the principal which is not accepted by the action
+	if accepted.is_empty() {
+		accepted = candidates.iter().collect();
+	}
+
+	if let Some(principal) = accepted
+		.iter()
+		.find_map(|(kind, _, uid, _)| uid.as_ref().map(|uid| (*kind, uid.clone())))
+	{
+		return Ok(principal);
+	}
+
+	let mut tokens: Vec<&str> = accepted.iter().map(|(.., tokens)| *tokens).collect();
+	tokens.dedup();
+	Err(HandleError::MissingToken {
+		action: action.to_string(),
+		tokens: tokens.join(" or "),
+	})
 }
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use cedar_policy::RestrictedExpression;
+use serde_json::Value;
+
+use super::entity_shapes::ClaimError;
+use super::exp_parsers;
+
+/// Rules of the trusted issuer keyed by the entity attribute name,
+/// attributes without rule are taken from the claims with the same name.
+pub type ClaimMapping = BTreeMap<String, ClaimRule>;
+
+/// Where the value of the entity attribute is taken from and how it is parsed.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ClaimRule {
+	/// Name of the claim, nested claims are selected by dotted path like `status.status_list.idx`,
+	/// the attribute name is used by default
+	pub claim: Option<String>,
+	/// Without parser the claim is converted to the attribute type declared in the schema
+	pub parser: Option<ClaimParser>,
+}
+
+/// Built-in parsers of the claim values.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimParser {
+	/// `email_address` record `{ id, domain }`
+	Email,
+	/// `Url` record `{ protocol, host, path }`
+	Url,
+	/// `ipaddr` extension value
+	Ip,
+	/// `decimal` extension value, claim can be string or number
+	Decimal,
+	/// RFC 3339 date time converted to `Long` unix timestamp in seconds,
+	/// numeric claims are used as is
+	Datetime,
+}
+
+impl ClaimParser {
+	pub(crate) fn parse(
+		self,
+		value: &Value,
+		claim: &str,
+	) -> Result<RestrictedExpression, ClaimError> {
+		let type_error = |expected| ClaimError::Type {
+			claim: claim.to_owned(),
+			expected,
+			value: value.clone(),
+		};
+
+		match (self, value) {
+			(Self::Email, Value::String(v)) => {
+				exp_parsers::email_exp(v).map_err(|source| ClaimError::Email {
+					claim: claim.to_owned(),
+					source,
+				})
+			}
+			(Self::Url, Value::String(v)) => {
+				exp_parsers::url_exp(v).map_err(|source| ClaimError::Url {
+					claim: claim.to_owned(),
+					source,
+				})
+			}
+			(Self::Ip, Value::String(v)) => Ok(RestrictedExpression::new_ip(v)),
+			(Self::Decimal, Value::String(v)) => Ok(RestrictedExpression::new_decimal(v)),
+			(Self::Decimal, Value::Number(v)) => {
+				Ok(RestrictedExpression::new_decimal(v.to_string()))
+			}
+			(Self::Datetime, Value::String(v)) => chrono::DateTime::parse_from_rfc3339(v)
+				.map(|datetime| RestrictedExpression::new_long(datetime.timestamp()))
+				.map_err(|err| ClaimError::Expression {
+					claim: claim.to_owned(),
+					message: format!("could not parse datetime: {err}"),
+				}),
+			(Self::Datetime, Value::Number(v)) => v
+				.as_i64()
+				.map(RestrictedExpression::new_long)
+				.ok_or_else(|| type_error("an integer timestamp")),
+			(Self::Decimal | Self::Datetime, _) => Err(type_error("a string or number")),
+			(Self::Email | Self::Url | Self::Ip, _) => Err(type_error("a string")),
+		}
+	}
+}
@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use cedar_policy::{EntityId, EntityTypeName, EntityUid, RestrictedExpression};
+use serde_json::Value;
+
+use super::claim_mapping::ClaimMapping;
+use super::exp_parsers;
+use super::jwt_tokens::claim_by_path;
+
+/// Type of the entity attribute declared in the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrType {
+	String,
+	Long,
+	Boolean,
+	Set(Box<AttrType>),
+	Record {
+		// full name of the common type if the record is declared by it
+		name: Option<String>,
+		attributes: BTreeMap<String, Attr>,
+	},
+	// full name of the entity type
+	Entity(String),
+	// `ipaddr` or `decimal`
+	Extension(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attr {
+	pub ty: AttrType,
+	pub required: bool,
+}
+
+/// Attributes of the entity types and of the action contexts declared in the schema.
+#[derive(Debug, Clone, Default)]
+pub struct EntityShapes {
+	// keyed by full type name like `Jans::User`
+	entities: HashMap<String, BTreeMap<String, Attr>>,
+	// keyed by action uid like `Jans::Action::"Execute"`
+	contexts: HashMap<EntityUid, BTreeMap<String, Attr>>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClaimError {
+	#[error("required claim `{0}` is missing")]
+	Missing(String),
+	#[error("claim `{claim}` is expected to be {expected}, but it is {value}")]
+	Type {
+		claim: String,
+		expected: &'static str,
+		value: Value,
+	},
+	#[error("could not parse claim `{claim}` as email: {source}")]
+	Email {
+		claim: String,
+		source: exp_parsers::ParseEmailToExpError,
+	},
+	#[error("could not parse claim `{claim}` as url: {source}")]
+	Url {
+		claim: String,
+		source: exp_parsers::ParseURLToExpError,
+	},
+	#[error("claim `{claim}` has unsupported type `{ty}`")]
+	Unsupported { claim: String, ty: String },
+	#[error("could not create value of claim `{claim}`: {message}")]
+	Expression { claim: String, message: String },
+}
+
+// schema in JSON format refers types declared in the same namespace by short name
+struct Namespace<'a> {
+	name: &'a str,
+	common_types: Option<&'a serde_json::Map<String, Value>>,
+}
+
+impl Namespace<'_> {
+	fn full_name(&self, name: &str) -> String {
+		if self.name.is_empty() || name.contains("::") {
+			name.to_owned()
+		} else {
+			format!("{}::{}", self.name, name)
+		}
+	}
+
+	fn attr_type(&self, ty: &Value) -> Result<AttrType, String> {
+		let name = ty["type"]
+			.as_str()
+			.ok_or_else(|| format!("type without name: {ty}"))?;
+
+		Ok(match name {
+			"String" => AttrType::String,
+			"Long" => AttrType::Long,
+			"Boolean" => AttrType::Boolean,
+			"Set" => AttrType::Set(Box::new(self.attr_type(&ty["element"])?)),
+			"Record" => AttrType::Record {
+				name: None,
+				attributes: self.attributes(&ty["attributes"])?,
+			},
+			"Entity" => AttrType::Entity(
+				self.full_name(
+					ty["name"]
+						.as_str()
+						.ok_or_else(|| format!("entity type without name: {ty}"))?,
+				),
+			),
+			"Extension" => AttrType::Extension(
+				ty["name"]
+					.as_str()
+					.ok_or_else(|| format!("extension type without name: {ty}"))?
+					.to_owned(),
+			),
+			common_type => {
+				let short_name = common_type.rsplit("::").next().unwrap_or(common_type);
+				let declared = self
+					.common_types
+					.and_then(|types| types.get(short_name))
+					.ok_or_else(|| format!("unknown type `{common_type}`"))?;
+				match self.attr_type(declared)? {
+					AttrType::Record {
+						name: None,
+						attributes,
+					} => AttrType::Record {
+						name: Some(self.full_name(short_name)),
+						attributes,
+					},
+					declared => declared,
+				}
+			}
+		})
+	}
+
+	fn attributes(&self, attributes: &Value) -> Result<BTreeMap<String, Attr>, String> {
+		let Some(attributes) = attributes.as_object() else {
+			return Ok(BTreeMap::new());
+		};
+		attributes
+			.iter()
+			.map(|(name, ty)| {
+				let attr = Attr {
+					ty: self.attr_type(ty)?,
+					required: ty["required"].as_bool().unwrap_or(true),
+				};
+				Ok((name.to_owned(), attr))
+			})
+			.collect()
+	}
+}
+
+impl EntityShapes {
+	/// Read attributes of the entity types and action contexts from the schema in Cedar JSON format.
+	pub(crate) fn from_schema_json(schema: &Value) -> Result<Self, String> {
+		let mut shapes = HashMap::new();
+		let mut contexts = HashMap::new();
+		for (namespace_name, namespace) in schema.as_object().into_iter().flatten() {
+			let namespace_ctx = Namespace {
+				name: namespace_name,
+				common_types: namespace["commonTypes"].as_object(),
+			};
+			for (name, entity_type) in namespace["entityTypes"].as_object().into_iter().flatten() {
+				let attributes = match entity_type.get("shape") {
+					Some(shape) => match namespace_ctx.attr_type(shape)? {
+						AttrType::Record { attributes, .. } => attributes,
+						_ => return Err(format!("shape of entity type `{name}` is not a record")),
+					},
+					None => BTreeMap::new(),
+				};
+				shapes.insert(namespace_ctx.full_name(name), attributes);
+			}
+			for (name, action) in namespace["actions"].as_object().into_iter().flatten() {
+				let attributes = match action["appliesTo"].get("context") {
+					Some(context) => match namespace_ctx.attr_type(context)? {
+						AttrType::Record { attributes, .. } => attributes,
+						_ => return Err(format!("context of action `{name}` is not a record")),
+					},
+					None => BTreeMap::new(),
+				};
+				let type_name = EntityTypeName::from_str(&namespace_ctx.full_name("Action"))
+					.map_err(|err| format!("invalid namespace `{namespace_name}`: {err}"))?;
+				let uid = EntityUid::from_type_name_and_id(type_name, EntityId::new(name));
+				contexts.insert(uid, attributes);
+			}
+		}
+		Ok(Self {
+			entities: shapes,
+			contexts,
+		})
+	}
+
+	/// Check that the entity type `type_name` is declared in the schema.
+	pub(crate) fn has_entity_type(&self, type_name: &str) -> bool {
+		self.entities.contains_key(type_name)
+	}
+
+	/// Check that the context of `action` declares the attribute `name`.
+	pub(crate) fn context_has_attr(&self, action: &EntityUid, name: &str) -> bool {
+		self.contexts
+			.get(action)
+			.is_some_and(|attributes| attributes.contains_key(name))
+	}
+
+	/// Check that the entity type `type_name` declares the attribute `name`.
+	pub(crate) fn has_attr(&self, type_name: &str, name: &str) -> bool {
+		self.entities
+			.get(type_name)
+			.is_some_and(|attributes| attributes.contains_key(name))
+	}
+
+	/// Attributes of the entity of `type_name` taken from the claims by `mapping` rules
+	/// or from the claims with the same names, claims which are not declared in the schema are skipped.
+	///
+	/// Token entities keep all claims of their token, the schema selects which of them become attributes.
+	pub(crate) fn entity_attrs(
+		&self,
+		type_name: &str,
+		claims: &Value,
+		mapping: &ClaimMapping,
+	) -> Result<HashMap<String, RestrictedExpression>, ClaimError> {
+		let Some(attributes) = self.entities.get(type_name) else {
+			return Ok(HashMap::new());
+		};
+
+		let mut attrs = HashMap::new();
+		for (name, attr) in attributes {
+			let rule = mapping.get(name);
+			let claim = rule.and_then(|rule| rule.claim.as_deref()).unwrap_or(name);
+			let Some(value) = claim_by_path(claims, claim).filter(|v| !v.is_null()) else {
+				if attr.required {
+					return Err(ClaimError::Missing(claim.to_owned()));
+				}
+				continue;
+			};
+
+			let value = match rule.and_then(|rule| rule.parser) {
+				Some(parser) => parser.parse(value, claim)?,
+				None => expression(&attr.ty, value, claim)?,
+			};
+			attrs.insert(name.to_owned(), value);
+		}
+		Ok(attrs)
+	}
+}
+
+fn record_attrs(
+	attributes: &BTreeMap<String, Attr>,
+	claims: &Value,
+	path: &str,
+) -> Result<HashMap<String, RestrictedExpression>, ClaimError> {
+	let mut attrs = HashMap::new();
+	for (name, attr) in attributes {
+		let claim = format!("{path}{name}");
+		match claims.get(name) {
+			None | Some(Value::Null) if attr.required => return Err(ClaimError::Missing(claim)),
+			None | Some(Value::Null) => {}
+			Some(value) => {
+				attrs.insert(name.to_owned(), expression(&attr.ty, value, &claim)?);
+			}
+		}
+	}
+	Ok(attrs)
+}
+
+fn type_error(claim: &str, expected: &'static str, value: &Value) -> ClaimError {
+	ClaimError::Type {
+		claim: claim.to_owned(),
+		expected,
+		value: value.clone(),
+	}
+}
+
+// value of the claim converted to the type declared in the schema
+fn expression(
+	ty: &AttrType,
+	value: &Value,
+	claim: &str,
+) -> Result<RestrictedExpression, ClaimError> {
+	match (ty, value) {
+		(AttrType::String, Value::String(v)) => Ok(RestrictedExpression::new_string(v.to_owned())),
+		(AttrType::String, _) => Err(type_error(claim, "a string", value)),
+
+		(AttrType::Long, _) => value
+			.as_i64()
+			.map(RestrictedExpression::new_long)
+			.ok_or_else(|| type_error(claim, "an integer", value)),
+
+		(AttrType::Boolean, Value::Bool(v)) => Ok(RestrictedExpression::new_bool(*v)),
+		(AttrType::Boolean, _) => Err(type_error(claim, "a boolean", value)),
+
+		(AttrType::Set(element), Value::Array(list)) => {
+			let elements = list
+				.iter()
+				.map(|v| expression(element, v, claim))
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok(RestrictedExpression::new_set(elements))
+		}
+		// single value of the claim like `aud` is the set with one element
+		(AttrType::Set(element), _) => Ok(RestrictedExpression::new_set([expression(
+			element, value, claim,
+		)?])),
+
+		(AttrType::Record { attributes, .. }, Value::Object(_)) => {
+			let attrs = record_attrs(attributes, value, &format!("{claim}."))?;
+			RestrictedExpression::new_record(attrs).map_err(|err| ClaimError::Expression {
+				claim: claim.to_owned(),
+				message: err.to_string(),
+			})
+		}
+		(
+			AttrType::Record {
+				name: Some(name), ..
+			},
+			Value::String(v),
+		) => string_record(name, v, claim),
+		(AttrType::Record { .. }, _) => Err(type_error(claim, "an object", value)),
+
+		(AttrType::Entity(type_name), Value::String(id)) => {
+			let type_name =
+				EntityTypeName::from_str(type_name).map_err(|err| ClaimError::Expression {
+					claim: claim.to_owned(),
+					message: err.to_string(),
+				})?;
+			Ok(RestrictedExpression::new_entity_uid(
+				EntityUid::from_type_name_and_id(type_name, EntityId::new(id)),
+			))
+		}
+		(AttrType::Entity(_), _) => Err(type_error(claim, "an entity id", value)),
+
+		(AttrType::Extension(name), Value::String(v)) if name == "ipaddr" => {
+			Ok(RestrictedExpression::new_ip(v))
+		}
+		(AttrType::Extension(name), Value::String(v)) if name == "decimal" => {
+			Ok(RestrictedExpression::new_decimal(v))
+		}
+		(AttrType::Extension(name), Value::Number(v)) if name == "decimal" => {
+			Ok(RestrictedExpression::new_decimal(v.to_string()))
+		}
+		(AttrType::Extension(name), Value::String(_)) => Err(ClaimError::Unsupported {
+			claim: claim.to_owned(),
+			ty: name.to_owned(),
+		}),
+		(AttrType::Extension(_), _) => Err(type_error(claim, "a string", value)),
+	}
+}
+
+// string claims are parsed to the records of `email_address` and `Url` types declared in the schema,
+// other records are created from the object claims or by the parser of the claim mapping rule
+fn string_record(name: &str, value: &str, claim: &str) -> Result<RestrictedExpression, ClaimError> {
+	match name.rsplit("::").next() {
+		Some("email_address") => {
+			exp_parsers::email_exp(value).map_err(|source| ClaimError::Email {
+				claim: claim.to_owned(),
+				source,
+			})
+		}
+		Some("Url") => exp_parsers::url_exp(value).map_err(|source| ClaimError::Url {
+			claim: claim.to_owned(),
+			source,
+		}),
+		_ => Err(type_error(
+			claim,
+			"an object",
+			&Value::String(value.to_owned()),
+		)),
+	}
+}
@@ -1,10 +1,10 @@
 use cedar_policy::{
 	Entity, EntityAttrEvaluationError, EntityId, EntityTypeName, EntityUid, RestrictedExpression,
 };
-use std::{
-	collections::{HashMap, HashSet},
-	str::FromStr,
-};
+use std::{collections::HashSet, str::FromStr};
+
+use super::claim_mapping::ClaimMapping;
+use super::entity_shapes::{ClaimError, EntityShapes};
 
 #[derive(thiserror::Error, Debug)]
 
@@ -82,21 +82,44 @@ pub fn url_exp(url_raw: &str) -> Result<RestrictedExpression, ParseURLToExpError
 
 #[derive(thiserror::Error, Debug)]
 pub enum TrustedIssuerEntityError {
-	#[error("could not get url exp: {0}")]
-	Parse(#[from] ParseURLToExpError),
+	#[error("could not create attributes: {0}")]
+	Attrs(Box<ClaimError>),
 
 	#[error("could not create entity uid from json: {0}")]
 	CreateFromJson(String),
 	#[error("could not create new entity: {0}")]
-	NewEntity(#[from] EntityAttrEvaluationError),
+	NewEntity(Box<EntityAttrEvaluationError>),
+}
+
+impl From<EntityAttrEvaluationError> for TrustedIssuerEntityError {
+	fn from(err: EntityAttrEvaluationError) -> Self {
+		Self::NewEntity(Box::new(err))
+	}
 }
 
-pub fn trusted_issuer_entity(url_raw: &str) -> Result<Entity, TrustedIssuerEntityError> {
+impl From<ClaimError> for TrustedIssuerEntityError {
+	fn from(err: ClaimError) -> Self {
+		Self::Attrs(Box::new(err))
+	}
+}
+
+pub fn trusted_issuer_uid(url_raw: &str) -> Result<EntityUid, TrustedIssuerEntityError> {
 	let id = serde_json::json!({ "__entity": { "type": "Jans::TrustedIssuer", "id": url_raw } });
-	let uid = EntityUid::from_json(id)
-		.map_err(|err| TrustedIssuerEntityError::CreateFromJson(err.to_string()))?;
+	EntityUid::from_json(id)
+		.map_err(|err| TrustedIssuerEntityError::CreateFromJson(err.to_string()))
+}
+
+/// Entity of the issuer `url_raw`, `name` is taken from the trusted issuer of the policy store.
+/// Attributes are set only if they are declared in the schema, like the attributes of the token entities.
+pub fn trusted_issuer_entity(
+	url_raw: &str,
+	name: Option<&str>,
+	shapes: &EntityShapes,
+) -> Result<Entity, TrustedIssuerEntityError> {
+	let uid = trusted_issuer_uid(url_raw)?;
 
-	let attrs = HashMap::from([("issuer_entity_id".to_string(), url_exp(url_raw)?)]);
+	let values = serde_json::json!({ "issuer_entity_id": url_raw, "name": name });
+	let attrs = shapes.entity_attrs("Jans::TrustedIssuer", &values, &ClaimMapping::new())?;
 
 	let entity = Entity::new(uid, attrs, HashSet::with_capacity(0))?;
 
@@ -105,7 +128,7 @@ pub fn trusted_issuer_entity(url_raw: &str) -> Result<Entity, TrustedIssuerEntit
 
 pub fn roles_entities(roles: &[String]) -> Vec<Entity> {
 	roles
-		.into_iter()
+		.iter()
 		.map(|role| {
 			Entity::with_uid(EntityUid::from_type_name_and_id(
 				// it should newer panic
@@ -3,14 +3,27 @@ use std::str::FromStr;
 
 use cedar_policy::{Entity, EntityId, EntityTypeName, EntityUid, ParseErrors};
 
-use super::jwt_tokens::{AccessToken, EntityCreatingError, IdToken, UserInfoToken, UserMissedInfo};
+use super::claim_mapping::ClaimMapping;
+use super::entity_shapes::EntityShapes;
+use super::exp_parsers::{trusted_issuer_entity, TrustedIssuerEntityError};
+use super::jwt_tokens::{
+	claim_by_path, user_entities, AccessToken, EntityCreatingError, IdToken, TransactionToken,
+	UserData, UserInfoToken,
+};
+use super::policy_store::{find_trusted_issuer, TrustedIssuers};
 
 #[derive(serde::Deserialize, Debug)]
 pub struct AuthzInputRaw {
-	// generates entities
-	pub id_token: String,
-	pub userinfo_token: String,
-	pub access_token: String,
+	// generates entities, any of the tokens can be omitted
+	#[serde(default)]
+	pub id_token: Option<String>,
+	#[serde(default)]
+	pub userinfo_token: Option<String>,
+	#[serde(default)]
+	pub access_token: Option<String>,
+	// optional transaction token of the service chain
+	#[serde(default)]
+	pub tx_token: Option<String>,
 
 	#[serde(flatten)]
 	pub extra: CedarParams,
@@ -47,6 +60,7 @@ impl ResourceData {
 	}
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug)]
 pub enum DecodeTokensError {
 	#[error("could not decode id_token: {0}")]
@@ -55,27 +69,67 @@ pub enum DecodeTokensError {
 	UserInfoToken(jwt::DecodeError),
 	#[error("could not decode access_token: {0}")]
 	AccessToken(jwt::DecodeError),
+	#[error("could not decode tx_token: {0}")]
+	TransactionToken(jwt::DecodeError),
+}
+
+// decode token keeping all claims of the token
+fn decode_token<T: serde::de::DeserializeOwned>(
+	decoder: &jwt::JWTDecoder,
+	jwt: &str,
+) -> Result<(T, serde_json::Value), jwt::DecodeError> {
+	let claims: serde_json::Value = decoder.decode(jwt)?;
+	let token = serde_json::from_value(claims.clone())
+		.map_err(|err| jwt::DecodeError::UnableToParseJson(err, claims.to_string()))?;
+	Ok((token, claims))
+}
+
+// decode the token if it is given
+fn decode_optional_token<T: serde::de::DeserializeOwned>(
+	decoder: &jwt::JWTDecoder,
+	jwt: Option<&str>,
+	error: fn(jwt::DecodeError) -> DecodeTokensError,
+) -> Result<Option<(T, serde_json::Value)>, DecodeTokensError> {
+	jwt.map(|jwt| decode_token(decoder, jwt).map_err(error))
+		.transpose()
 }
 
 impl AuthzInputRaw {
 	pub fn decode_tokens(self, decoder: &jwt::JWTDecoder) -> Result<AuthzInput, DecodeTokensError> {
-		let id_token: IdToken = decoder
-			.decode(&self.id_token)
-			.map_err(DecodeTokensError::IdToken)?;
+		let id_token = decode_optional_token(
+			decoder,
+			self.id_token.as_deref(),
+			DecodeTokensError::IdToken,
+		)?
+		.map(|(token, claims)| IdToken { claims, ..token });
+
+		let userinfo_token = decode_optional_token(
+			decoder,
+			self.userinfo_token.as_deref(),
+			DecodeTokensError::UserInfoToken,
+		)?
+		.map(|(token, claims)| UserInfoToken { claims, ..token });
 
-		let userinfo_token: UserInfoToken = decoder
-			.decode(&self.userinfo_token)
-			.map_err(DecodeTokensError::UserInfoToken)?;
+		let access_token = decode_optional_token(
+			decoder,
+			self.access_token.as_deref(),
+			DecodeTokensError::AccessToken,
+		)?
+		.map(|(token, claims)| AccessToken { claims, ..token });
 
-		let access_token: AccessToken = decoder
-			.decode(&self.access_token)
-			.map_err(DecodeTokensError::AccessToken)?;
+		let tx_token = decode_optional_token(
+			decoder,
+			self.tx_token.as_deref(),
+			DecodeTokensError::TransactionToken,
+		)?
+		.map(|(token, claims)| TransactionToken { claims, ..token });
 
 		Ok(AuthzInput {
 			jwt: JWTData {
 				id_token,
 				userinfo_token,
 				access_token,
+				tx_token,
 			},
 			chedar_params: self.extra,
 		})
@@ -84,9 +138,10 @@ impl AuthzInputRaw {
 
 #[derive(Debug)]
 pub struct JWTData {
-	pub id_token: IdToken,
-	pub userinfo_token: UserInfoToken,
-	pub access_token: AccessToken,
+	pub id_token: Option<IdToken>,
+	pub userinfo_token: Option<UserInfoToken>,
+	pub access_token: Option<AccessToken>,
+	pub tx_token: Option<TransactionToken>,
 }
 
 #[derive(Debug)]
@@ -97,6 +152,7 @@ pub struct AuthzInput {
 	pub chedar_params: CedarParams,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug)]
 pub enum AuthzInputEntitiesError {
 	#[error("could not get id token entity from id_token: {0}")]
@@ -111,68 +167,521 @@ pub enum AuthzInputEntitiesError {
 	AccessTokenEntity(EntityCreatingError),
 	#[error("could not get application entity from access_token: {0}")]
 	ApplicationEntity(EntityCreatingError),
+
+	#[error("could not get transaction token entity from tx_token: {0}")]
+	TransactionTokenEntity(EntityCreatingError),
+
+	#[error("could not create trusted issuer entity: {0}")]
+	TrustedIssuerEntity(#[from] TrustedIssuerEntityError),
+
+	#[error("claim `{0}` configured as principal identifier is not a string")]
+	PrincipalIdentifier(String),
+
+	#[error("id_token was not issued for this client: id_token.aud {aud} != access_token.client_id {client_id}")]
+	IdTokenAudienceMismatch { aud: String, client_id: String },
+	#[error("access_token and id_token weren't issued by the same issuer: access_token.iss {access_token_iss} != id_token.iss {id_token_iss}")]
+	IssuerMismatch {
+		access_token_iss: String,
+		id_token_iss: String,
+	},
+	#[error(
+		"userinfo_token invalid: userinfo_token.sub {userinfo_sub} != id_token.sub {id_token_sub}"
+	)]
+	UserInfoSubjectMismatch {
+		userinfo_sub: String,
+		id_token_sub: String,
+	},
+	#[error(
+		"userinfo_token invalid: userinfo_token.iss {userinfo_iss} != id_token.iss {id_token_iss}"
+	)]
+	UserInfoIssuerMismatch {
+		userinfo_iss: String,
+		id_token_iss: String,
+	},
+}
+
+/// Checks that tokens of one input were issued for the same client and user.
+#[derive(Debug, Clone)]
+pub struct TokensConsistencyConfig {
+	// `id_token.aud` must be equal to `access_token.client_id`
+	pub id_token_audience: bool,
+	// `id_token.iss` must be equal to `access_token.iss`
+	pub same_issuer: bool,
+	// `userinfo_token.sub` must be equal to `id_token.sub`
+	pub userinfo_subject: bool,
+	// `userinfo_token.iss` must be equal to `id_token.iss`
+	pub userinfo_issuer: bool,
+}
+
+impl Default for TokensConsistencyConfig {
+	fn default() -> Self {
+		Self {
+			id_token_audience: true,
+			same_issuer: true,
+			userinfo_subject: true,
+			userinfo_issuer: true,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+	IdToken,
+	UserinfoToken,
+	AccessToken,
+	TransactionToken,
+}
+
+impl TokenKind {
+	pub(crate) const ALL: [Self; 4] = [
+		Self::IdToken,
+		Self::UserinfoToken,
+		Self::AccessToken,
+		Self::TransactionToken,
+	];
+
+	/// Entity types which attributes are taken by the claim mapping rules of the token kind.
+	pub(crate) fn mapped_entity_types(self) -> &'static [&'static str] {
+		match self {
+			Self::IdToken => &["Jans::id_token"],
+			Self::UserinfoToken => &["Jans::Userinfo_token", "Jans::User"],
+			Self::AccessToken => &["Jans::Access_token"],
+			Self::TransactionToken => &["Jans::Transaction_token"],
+		}
+	}
+}
+
+impl std::fmt::Display for TokenKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::IdToken => "id_token",
+			Self::UserinfoToken => "userinfo_token",
+			Self::AccessToken => "access_token",
+			Self::TransactionToken => "tx_token",
+		})
+	}
+}
+
+/// What to do with token which kind is not trusted by its issuer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UntrustedTokenMode {
+	/// authorization request fails
+	#[default]
+	Reject,
+	/// token is handled as if it is not given: no entities are created from its claims
+	Ignore,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TrustedIssuerError {
+	#[error("{kind} is issued by not trusted issuer: {iss}")]
+	UntrustedIssuer { kind: TokenKind, iss: String },
+	#[error("{kind} of issuer {iss} is not trusted by configuration")]
+	UntrustedToken { kind: TokenKind, iss: String },
+}
+
+/// Parameters of entities creation from the tokens.
+pub struct EntitiesConfig<'a> {
+	pub application_name: Option<&'a str>,
+	pub consistency: &'a TokensConsistencyConfig,
+	pub trusted_issuers: &'a TrustedIssuers,
+	// attributes of the entities declared in the schema
+	pub entity_shapes: &'a EntityShapes,
+	// tokens which entities are not added to the list
+	pub ignored_tokens: &'a [TokenKind],
 }
 
 pub struct JWTDataEntities {
 	pub entities: Vec<Entity>,
-	pub user_entity_uid: EntityUid,
+	// user entity if id_token or userinfo_token is given
+	pub user_entity_uid: Option<EntityUid>,
+	// client entity if access_token is given
+	pub client_entity_uid: Option<EntityUid>,
+	// application entity if access_token is given and the application name is configured
+	pub application_entity_uid: Option<EntityUid>,
+	// entity of the tx_token if it is given and trusted
+	pub tx_token_uid: Option<EntityUid>,
 }
 
 impl JWTData {
+	/// Check that every token is issued by trusted issuer,
+	/// returns tokens which kind is not trusted by the issuer if they should be ignored.
+	pub fn check_trusted_issuers(
+		&self,
+		issuers: &TrustedIssuers,
+		mode: UntrustedTokenMode,
+	) -> Result<Vec<TokenKind>, TrustedIssuerError> {
+		let mut ignored = Vec::new();
+		for (kind, iss) in self.token_issuers() {
+			let issuer = find_trusted_issuer(issuers, iss).ok_or_else(|| {
+				TrustedIssuerError::UntrustedIssuer {
+					kind,
+					iss: iss.to_owned(),
+				}
+			})?;
+
+			let trusted = match kind {
+				TokenKind::IdToken => issuer.id_tokens.trusted,
+				TokenKind::UserinfoToken => issuer.userinfo_tokens.trusted,
+				TokenKind::AccessToken => issuer.access_tokens.trusted,
+				TokenKind::TransactionToken => issuer.tx_tokens.trusted,
+			};
+			if trusted {
+				continue;
+			}
+
+			match mode {
+				UntrustedTokenMode::Reject => {
+					return Err(TrustedIssuerError::UntrustedToken {
+						kind,
+						iss: iss.to_owned(),
+					})
+				}
+				UntrustedTokenMode::Ignore => {
+					log::debug!("{kind} of issuer {iss} is not trusted, the token is ignored");
+					ignored.push(kind);
+				}
+			}
+		}
+		Ok(ignored)
+	}
+
 	pub fn entities(
 		self,
-		application_name: Option<&str>,
+		config: EntitiesConfig,
 	) -> Result<JWTDataEntities, AuthzInputEntitiesError> {
-		// TODO: implement check of token correctness
-		// // check if `aud` claim in id_token matches `client_id` in access token
-		// if id_token.aud != access_token.client_id && super::REQUIRE_AUD_VALIDATION.get().cloned().unwrap_or(false) {
-		// 	throw_str("id_token was not issued for this client: (id_token.aud != access_token.client_id)")
-		// }
-
-		// // check if both tokens were issued by the same issuer
-		// if id_token.iss != access_token.iss {
-		// 	throw_str("access_token and id_token weren't issued by the same issuer: (access_token.iss != id_token.iss)")
-		// }
-		// if userinfo.sub != id_token.sub || userinfo.iss != id_token.iss {
-		// 	throw_str("userinfo token invalid: either sub or iss doesn't match id_token")
-		// }
-
-		let id_token_entities = self
-			.id_token
-			.entities()
-			.map_err(AuthzInputEntitiesError::IdTokenEntity)?;
-
-		let user_info_entities = self
-			.userinfo_token
-			.entities(UserMissedInfo {
-				roles: &[], //TODO: add roles after adding to jwts
-				// according to doc
-				// User: Created based on the joined id_token and userinfo token. sub is the entity identifier
-				// but username only has in access_token
-				username: self.access_token.username.clone(),
-			})
-			.map_err(AuthzInputEntitiesError::UserTokenEntity)?;
+		let data = self.without_tokens(config.ignored_tokens);
+		data.check_consistency(config.consistency)?;
+		data.trusted_entities(config)
+	}
 
-		let access_token_entities = self
-			.access_token
-			.entities(application_name)
-			.map_err(AuthzInputEntitiesError::AccessTokenEntity)?;
+	// ignored tokens are dropped before anything is created from them: claims of a token of
+	// untrusted issuer must not define the principal, its roles or attributes
+	fn without_tokens(mut self, ignored: &[TokenKind]) -> Self {
+		for kind in ignored {
+			match kind {
+				TokenKind::IdToken => self.id_token = None,
+				TokenKind::UserinfoToken => self.userinfo_token = None,
+				TokenKind::AccessToken => self.access_token = None,
+				TokenKind::TransactionToken => self.tx_token = None,
+			}
+		}
+		self
+	}
 
-		let mut list = id_token_entities;
-		list.extend(user_info_entities.entities);
-		list.extend(access_token_entities);
+	fn trusted_entities(
+		self,
+		config: EntitiesConfig,
+	) -> Result<JWTDataEntities, AuthzInputEntitiesError> {
+		let issuer_entities = self.issuer_entities(&config)?;
+		let mut list = Vec::new();
+
+		if let Some(id_token) = &self.id_token {
+			let entities = id_token
+				.entities(
+					config.entity_shapes,
+					claim_mapping(config.trusted_issuers, &id_token.iss, TokenKind::IdToken),
+				)
+				.map_err(AuthzInputEntitiesError::IdTokenEntity)?;
+			list.extend(entities);
+		}
+
+		if let Some(userinfo_token) = &self.userinfo_token {
+			let entities = userinfo_token
+				.entities(
+					config.entity_shapes,
+					claim_mapping(
+						config.trusted_issuers,
+						&userinfo_token.iss,
+						TokenKind::UserinfoToken,
+					),
+				)
+				.map_err(AuthzInputEntitiesError::UserTokenEntity)?;
+			list.extend(entities);
+		}
+
+		// according to doc
+		// User: Created based on the joined id_token and userinfo token. sub is the entity identifier
+		// but username only has in access_token
+		let mut user_entity_uid = None;
+		if let Some((user_id, iss)) = self
+			.user_id(config.trusted_issuers)?
+			.zip(self.user_issuer())
+		{
+			let roles = self.roles(config.trusted_issuers);
+			let user = user_entities(
+				UserData {
+					roles: &roles,
+					claims: self.user_claims(),
+					user_id,
+				},
+				config.entity_shapes,
+				claim_mapping(config.trusted_issuers, iss, TokenKind::UserinfoToken),
+			)
+			.map_err(AuthzInputEntitiesError::UserEntity)?;
+			list.extend(user.entities);
+			user_entity_uid = Some(user.user_entry_uid);
+		}
+
+		let mut client_entity_uid = None;
+		let mut application_entity_uid = None;
+		if let Some(access_token) = &self.access_token {
+			let client = access_token
+				.entities(
+					config.application_name,
+					config.entity_shapes,
+					claim_mapping(
+						config.trusted_issuers,
+						&access_token.iss,
+						TokenKind::AccessToken,
+					),
+				)
+				.map_err(AuthzInputEntitiesError::AccessTokenEntity)?;
+			list.extend(client.entities);
+			client_entity_uid = Some(client.client_entry_uid);
+			application_entity_uid = client.application_entry_uid;
+		}
+
+		// the entity can be used only if the schema declares its type
+		let mut tx_token_uid = None;
+		if let Some(tx_token) = self.tx_token.as_ref().filter(|_| {
+			config
+				.entity_shapes
+				.has_entity_type("Jans::Transaction_token")
+		}) {
+			let entities = tx_token
+				.entities(
+					config.entity_shapes,
+					claim_mapping(
+						config.trusted_issuers,
+						&tx_token.iss,
+						TokenKind::TransactionToken,
+					),
+				)
+				.map_err(AuthzInputEntitiesError::TransactionTokenEntity)?;
+			tx_token_uid = entities.first().map(Entity::uid);
+			list.extend(entities);
+		}
+
+		list.extend(issuer_entities);
 
 		Ok(JWTDataEntities {
 			entities: deduplicate_entities(list),
-			user_entity_uid: user_info_entities.user_entry_uid,
+			user_entity_uid,
+			client_entity_uid,
+			application_entity_uid,
+			tx_token_uid,
 		})
 	}
 }
 
+impl JWTData {
+	// issuers of the given tokens
+	fn token_issuers(&self) -> Vec<(TokenKind, &String)> {
+		[
+			self.id_token
+				.as_ref()
+				.map(|token| (TokenKind::IdToken, &token.iss)),
+			self.userinfo_token
+				.as_ref()
+				.map(|token| (TokenKind::UserinfoToken, &token.iss)),
+			self.access_token
+				.as_ref()
+				.map(|token| (TokenKind::AccessToken, &token.iss)),
+			self.tx_token
+				.as_ref()
+				.map(|token| (TokenKind::TransactionToken, &token.iss)),
+		]
+		.into_iter()
+		.flatten()
+		.collect()
+	}
+
+	// entities of issuers of the given tokens, tokens refer them by `iss` attribute
+	fn issuer_entities(
+		&self,
+		config: &EntitiesConfig,
+	) -> Result<Vec<Entity>, TrustedIssuerEntityError> {
+		self.token_issuers()
+			.into_iter()
+			.map(|(_, iss)| {
+				let name = find_trusted_issuer(config.trusted_issuers, iss)
+					.and_then(|issuer| issuer.name.as_deref());
+				trusted_issuer_entity(iss, name, config.entity_shapes)
+			})
+			.collect()
+	}
+
+	// issuer of userinfo_token or id_token, its configuration is used for the roles
+	// and claim mapping of the user entity
+	fn user_issuer(&self) -> Option<&str> {
+		self.userinfo_token
+			.as_ref()
+			.map(|token| token.iss.as_str())
+			.or_else(|| self.id_token.as_ref().map(|token| token.iss.as_str()))
+	}
+
+	// claims of the given tokens joined in order access_token, id_token, userinfo_token,
+	// the latter have priority
+	fn user_claims(&self) -> serde_json::Map<String, serde_json::Value> {
+		let mut claims = serde_json::Map::new();
+		let tokens = [
+			self.access_token.as_ref().map(|token| &token.claims),
+			self.id_token.as_ref().map(|token| &token.claims),
+			self.userinfo_token.as_ref().map(|token| &token.claims),
+		];
+		for token_claims in tokens.into_iter().flatten() {
+			if let Some(token_claims) = token_claims.as_object() {
+				claims.extend(token_claims.clone());
+			}
+		}
+		claims
+	}
+
+	// id of the user entity, taken from the claim configured as `principalIdentifier`
+	// of the id_token issuer, `sub` is used if the claim is not configured or missing,
+	// there is no user without id_token and userinfo_token
+	fn user_id(&self, issuers: &TrustedIssuers) -> Result<Option<String>, AuthzInputEntitiesError> {
+		let (id_token, userinfo_token) = (self.id_token.as_ref(), self.userinfo_token.as_ref());
+		let Some(sub) = userinfo_token
+			.map(|token| &token.sub)
+			.or(id_token.map(|token| &token.sub))
+		else {
+			return Ok(None);
+		};
+
+		let iss = id_token
+			.map(|token| &token.iss)
+			.or(userinfo_token.map(|token| &token.iss));
+		let claim = iss
+			.and_then(|iss| find_trusted_issuer(issuers, iss))
+			.and_then(|issuer| issuer.id_tokens.principal_identifier.as_deref());
+		let Some(claim) = claim else {
+			return Ok(Some(sub.clone()));
+		};
+
+		let value = [
+			id_token.map(|token| &token.claims),
+			userinfo_token.map(|token| &token.claims),
+		]
+		.into_iter()
+		.flatten()
+		.find_map(|claims| claims.get(claim).filter(|v| !v.is_null()));
+
+		match value {
+			None => {
+				log::debug!("principal identifier claim `{claim}` is missing, `sub` is used");
+				Ok(Some(sub.clone()))
+			}
+			Some(serde_json::Value::String(id)) => Ok(Some(id.clone())),
+			Some(serde_json::Value::Number(id)) => Ok(Some(id.to_string())),
+			Some(_) => Err(AuthzInputEntitiesError::PrincipalIdentifier(
+				claim.to_owned(),
+			)),
+		}
+	}
+
+	// roles from the claim configured as `roleMapping` of the userinfo_token issuer,
+	// claim is searched in userinfo_token, id_token and access_token
+	fn roles(&self, issuers: &TrustedIssuers) -> Vec<String> {
+		let Some(claim) = self
+			.user_issuer()
+			.and_then(|iss| find_trusted_issuer(issuers, iss))
+			.and_then(|issuer| issuer.userinfo_tokens.role_mapping.as_deref())
+		else {
+			return Vec::new();
+		};
+
+		let mut roles: Vec<String> = Vec::new();
+		let mut add_role = |role: &serde_json::Value| match role.as_str() {
+			Some(role) if !roles.iter().any(|r| r == role) => roles.push(role.to_owned()),
+			Some(_) => {}
+			None => log::warn!("role claim `{claim}` contains not string value: {role}"),
+		};
+
+		let tokens = [
+			self.userinfo_token.as_ref().map(|token| &token.claims),
+			self.id_token.as_ref().map(|token| &token.claims),
+			self.access_token.as_ref().map(|token| &token.claims),
+		];
+		for claims in tokens.into_iter().flatten() {
+			match claim_by_path(claims, claim) {
+				Some(serde_json::Value::Array(list)) => list.iter().for_each(&mut add_role),
+				Some(serde_json::Value::Null) | None => {}
+				Some(role) => add_role(role),
+			}
+		}
+		roles
+	}
+
+	// tokens are checked only if both of them are given
+	fn check_consistency(
+		&self,
+		config: &TokensConsistencyConfig,
+	) -> Result<(), AuthzInputEntitiesError> {
+		let (id_token, userinfo_token, access_token) = (
+			self.id_token.as_ref(),
+			self.userinfo_token.as_ref(),
+			self.access_token.as_ref(),
+		);
+
+		if let (true, Some(id_token), Some(access_token)) =
+			(config.id_token_audience, id_token, access_token)
+		{
+			if id_token.aud != access_token.client_id {
+				return Err(AuthzInputEntitiesError::IdTokenAudienceMismatch {
+					aud: id_token.aud.clone(),
+					client_id: access_token.client_id.clone(),
+				});
+			}
+		}
+
+		if let (true, Some(id_token), Some(access_token)) =
+			(config.same_issuer, id_token, access_token)
+		{
+			if id_token.iss != access_token.iss {
+				return Err(AuthzInputEntitiesError::IssuerMismatch {
+					access_token_iss: access_token.iss.clone(),
+					id_token_iss: id_token.iss.clone(),
+				});
+			}
+		}
+
+		if let (true, Some(id_token), Some(userinfo_token)) =
+			(config.userinfo_subject, id_token, userinfo_token)
+		{
+			if userinfo_token.sub != id_token.sub {
+				return Err(AuthzInputEntitiesError::UserInfoSubjectMismatch {
+					userinfo_sub: userinfo_token.sub.clone(),
+					id_token_sub: id_token.sub.clone(),
+				});
+			}
+		}
+
+		if let (true, Some(id_token), Some(userinfo_token)) =
+			(config.userinfo_issuer, id_token, userinfo_token)
+		{
+			if userinfo_token.iss != id_token.iss {
+				return Err(AuthzInputEntitiesError::UserInfoIssuerMismatch {
+					userinfo_iss: userinfo_token.iss.clone(),
+					id_token_iss: id_token.iss.clone(),
+				});
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// claim mapping rules of the issuer of the token, there are no rules if the issuer is not found
+fn claim_mapping<'a>(issuers: &'a TrustedIssuers, iss: &str, kind: TokenKind) -> &'a ClaimMapping {
+	static NO_RULES: ClaimMapping = ClaimMapping::new();
+	find_trusted_issuer(issuers, iss).map_or(&NO_RULES, |issuer| issuer.claim_mapping(kind))
+}
+
 fn deduplicate_entities(list: Vec<Entity>) -> Vec<Entity> {
 	// use Btree to not implement hash
 	BTreeMap::from_iter(list.into_iter().map(|e| (e.uid(), e)))
-		.into_iter()
-		.map(|(_k, v)| v)
+		.into_values()
 		.collect()
 }
@@ -5,129 +5,52 @@ use cedar_policy::EntityAttrEvaluationError;
 use cedar_policy::EntityUid;
 use cedar_policy::RestrictedExpression;
 
+use super::claim_mapping::ClaimMapping;
+use super::entity_shapes::{ClaimError, EntityShapes};
 use super::exp_parsers;
 
 #[derive(thiserror::Error, Debug)]
 pub enum EntityCreatingError {
 	#[error("could not create entity uid from json: {0}")]
 	CreateFromJson(String),
-	#[error("create expression with email: {0}")]
-	Email(#[from] exp_parsers::ParseEmailToExpError),
+	#[error("could not create entity attributes from claims: {0}")]
+	Claim(#[from] ClaimError),
 	#[error("could not create new entity: {0}")]
-	NewEntity(#[from] EntityAttrEvaluationError),
+	NewEntity(Box<EntityAttrEvaluationError>),
 
 	#[error("could not create new entity of trusted issuer: {0}")]
 	TrustedIssuer(#[from] exp_parsers::TrustedIssuerEntityError),
 }
 
+impl From<EntityAttrEvaluationError> for EntityCreatingError {
+	fn from(err: EntityAttrEvaluationError) -> Self {
+		Self::NewEntity(Box::new(err))
+	}
+}
+
 #[derive(Default, Debug, Clone, PartialEq, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IdToken {
-	pub acr: String,
-	pub amr: Vec<String>,
 	pub aud: String,
-	pub birthdate: String,
-	pub email: String,
-	pub exp: i64,
-	pub iat: i64,
 	pub iss: String,
 	pub jti: String,
-	pub name: String,
-	#[serde(rename = "phone_number")]
-	pub phone_number: String,
 	pub sub: String,
-	// next fields is unused for now
-	// #[serde(rename = "at_hash")]
-	// pub at_hash: String,
-	// pub country: String,
-
-	// #[serde(rename = "user_name")]
-	// pub user_name: String,
-
-	// pub inum: String,
-	// pub sid: String,
-	// #[serde(rename = "jansOpenIDConnectVersion")]
-	// pub jans_open_idconnect_version: String,
-
-	// #[serde(rename = "updated_at")]
-	// pub updated_at: i64,
-	// #[serde(rename = "auth_time")]
-	// pub auth_time: i64,
-	// pub nickname: String,
-
-	// #[serde(rename = "given_name")]
-	// pub given_name: String,
-	// #[serde(rename = "middle_name")]
-	// pub middle_name: String,
-	// pub nonce: String,
-
-	// #[serde(rename = "c_hash")]
-	// pub c_hash: String,
-	// #[serde(rename = "user_permission")]
-	// pub user_permission: Vec<String>,
-
-	// pub grant: String,
-	// #[serde(rename = "family_name")]
-	// pub family_name: String,
-	// pub status: Status,
-	// #[serde(rename = "jansAdminUIRole")]
-	// pub jans_admin_uirole: Vec<String>,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Status {
-	#[serde(rename = "status_list")]
-	pub status_list: StatusList,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct StatusList {
-	pub idx: i64,
-	pub uri: String,
+	#[serde(skip)]
+	pub claims: serde_json::Value,
 }
 
 impl IdToken {
-	pub fn entities(self) -> Result<Vec<Entity>, EntityCreatingError> {
-		let id = serde_json::json!({ "__entity": { "type": "Jans::id_token", "id": self.jti } });
+	pub(crate) fn entities(
+		&self,
+		shapes: &EntityShapes,
+		mapping: &ClaimMapping,
+	) -> Result<Vec<Entity>, EntityCreatingError> {
+		let type_name = "Jans::id_token";
+		let id = serde_json::json!({ "__entity": { "type": type_name, "id": self.jti } });
 		let uid = EntityUid::from_json(id)
 			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
 
-		let amr = self
-			.amr
-			.iter()
-			.map(|v| RestrictedExpression::new_string(v.to_owned()));
-
-		// TODO: add
-		//         iss: TrustedIssuer,
-
-		let mut attrs = HashMap::from([
-			("acr".into(), RestrictedExpression::new_string(self.acr)),
-			("amr".into(), RestrictedExpression::new_set(amr)),
-			("aud".into(), RestrictedExpression::new_string(self.aud)),
-			(
-				"birthdate".into(),
-				RestrictedExpression::new_string(self.birthdate),
-			),
-			("email".into(), exp_parsers::email_exp(&self.email)?),
-			("exp".into(), RestrictedExpression::new_long(self.exp)),
-			("iat".into(), RestrictedExpression::new_long(self.iat)),
-			("jti".into(), RestrictedExpression::new_string(self.jti)),
-			("name".into(), RestrictedExpression::new_string(self.name)),
-			(
-				"phone_number".into(),
-				RestrictedExpression::new_string(self.phone_number),
-			),
-			("sub".into(), RestrictedExpression::new_string(self.sub)),
-		]);
-
-		let trusted_issuer_entity = exp_parsers::trusted_issuer_entity(&self.iss)?;
-
-		attrs.insert(
-			"iss".into(),
-			RestrictedExpression::new_entity_uid(trusted_issuer_entity.uid()),
-		);
+		let attrs = shapes.entity_attrs(type_name, &self.claims, mapping)?;
 
 		let id_token_entity = Entity::new(uid, attrs, HashSet::with_capacity(0))?;
 		let result = vec![id_token_entity];
@@ -137,241 +60,139 @@ impl IdToken {
 
 #[derive(serde::Deserialize, Debug)]
 pub struct UserInfoToken {
-	pub aud: String,
-	pub birthdate: String,
-	pub email: String,
 	pub iss: String,
 	pub jti: String,
-	pub name: String,
-	#[serde(rename = "phone_number")]
-	pub phone_number: String,
 	pub sub: String,
-	// id of user
-	pub inum: String,
-	// next fields is unused
-	// pub country: String,
-	// #[serde(rename = "user_name")]
-	// pub user_name: String,
-	// #[serde(rename = "given_name")]
-	// pub given_name: String,
-	// #[serde(rename = "middle_name")]
-	// pub middle_name: String,
-
-	// #[serde(rename = "client_id")]
-	// pub client_id: String,
-	// #[serde(rename = "updated_at")]
-	// pub updated_at: i64,
-	// pub nickname: String,
-	// #[serde(rename = "user_permission")]
-	// pub user_permission: Vec<String>,
-	// #[serde(rename = "family_name")]
-	// pub family_name: String,
-	// #[serde(rename = "jansAdminUIRole")]
-	// pub jans_admin_uirole: Vec<String>,
+	#[serde(skip)]
+	pub claims: serde_json::Value,
 }
 
-// Restricted expressions can contain only the following:
-//   - bool, int, and string literals
-//   - literal `EntityUid`s such as `User::"alice"`
-//   - extension function calls, where the arguments must be other things
-//       on this list
-//   - set and record literals, where the values must be other things on
-//       this list
-#[allow(dead_code)]
-//it can be usefull to dynamically fill the entities data from token
-fn json_to_expression(value: serde_json::Value) -> Option<RestrictedExpression> {
-	match value {
-		serde_json::Value::Null => None,
-		serde_json::Value::Bool(v) => Some(RestrictedExpression::new_bool(v)),
-		serde_json::Value::Number(v) => {
-			if let Option::Some(i) = v.as_i64() {
-				Some(RestrictedExpression::new_long(i))
-			} else if let Option::Some(f) = v.as_f64() {
-				Some(RestrictedExpression::new_decimal(f.to_string()))
-			} else {
-				None
-			}
-		}
-		serde_json::Value::String(v) => Some(RestrictedExpression::new_string(v)),
-		serde_json::Value::Array(v) => Some(RestrictedExpression::new_set(
-			v.into_iter()
-				.filter_map(|v| json_to_expression(v))
-				.collect::<Vec<RestrictedExpression>>(),
-		)),
-		serde_json::Value::Object(_) => None,
+/// Get claim by name, nested claims can be selected by dotted path like `status.status_list.idx`.
+pub(crate) fn claim_by_path<'a>(
+	claims: &'a serde_json::Value,
+	path: &str,
+) -> Option<&'a serde_json::Value> {
+	// claim names can contain dots, so exact name has priority
+	if let Some(value) = claims.get(path) {
+		return Some(value);
 	}
+	path.split('.')
+		.try_fold(claims, |value, key| value.get(key))
 }
 
-pub(crate) struct UserInfoTokenEntityBox {
+pub(crate) struct UserEntityBox {
 	pub entities: Vec<Entity>,
 	pub user_entry_uid: EntityUid,
 }
 
-pub(crate) struct UserMissedInfo<'a> {
-	pub username: String,
+pub(crate) struct UserData<'a> {
+	// id of the user entity
+	pub user_id: String,
 	pub roles: &'a [String],
+	// joined claims of the given tokens, the user entity attributes are taken from them
+	pub claims: serde_json::Map<String, serde_json::Value>,
 }
 
 impl UserInfoToken {
 	pub(crate) fn entities(
 		&self,
-		user_info: UserMissedInfo,
-	) -> Result<UserInfoTokenEntityBox, EntityCreatingError> {
-		let mut entry_box = self.get_user_entities(user_info)?;
-		entry_box
-			.entities
-			.extend(self.get_user_info_tokens_entity()?);
-		Ok(entry_box)
-	}
-
-	fn get_user_info_tokens_entity(&self) -> Result<Vec<Entity>, EntityCreatingError> {
-		let id = serde_json::json!({ "__entity": { "type": "Jans::Userinfo_token", "id": self.jti.to_owned() } });
+		shapes: &EntityShapes,
+		mapping: &ClaimMapping,
+	) -> Result<Vec<Entity>, EntityCreatingError> {
+		let type_name = "Jans::Userinfo_token";
+		let id =
+			serde_json::json!({ "__entity": { "type": type_name, "id": self.jti.to_owned() } });
 		let uid = EntityUid::from_json(id)
 			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
 
-		let trusted_issuer_entity = exp_parsers::trusted_issuer_entity(&self.iss)?;
-
 		let parents = HashSet::new();
-		let attrs = HashMap::from([
-			(
-				"aud".to_owned(),
-				RestrictedExpression::new_string(self.aud.clone()),
-			),
-			(
-				"birthdate".to_string(),
-				RestrictedExpression::new_string(self.birthdate.clone()),
-			),
-			("email".to_string(), exp_parsers::email_exp(&self.email)?),
-			(
-				"iss".to_string(),
-				RestrictedExpression::new_entity_uid(trusted_issuer_entity.uid()),
-			),
-			(
-				"jti".to_string(),
-				RestrictedExpression::new_string(self.jti.clone()),
-			),
-			(
-				"name".to_string(),
-				RestrictedExpression::new_string(self.name.clone()),
-			),
-			(
-				"phone_number".to_string(),
-				RestrictedExpression::new_string(self.phone_number.clone()),
-			),
-			(
-				"sub".to_string(),
-				RestrictedExpression::new_string(self.sub.clone()),
-			),
-		]);
+		let attrs = shapes.entity_attrs(type_name, &self.claims, mapping)?;
 
 		let token_entity = Entity::new(uid, attrs, parents)?;
 
 		Ok(vec![token_entity])
 	}
+}
 
-	fn get_user_entities(
-		&self,
-		user_info: UserMissedInfo,
-	) -> Result<UserInfoTokenEntityBox, EntityCreatingError> {
-		let id = serde_json::json!({ "__entity": { "type": "Jans::User", "id": self.sub } });
-		let uid = EntityUid::from_json(id)
-			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
-
-		let attrs = HashMap::from([
-			(
-				"sub".to_string(),
-				RestrictedExpression::new_string(self.sub.clone()),
-			),
-			(
-				"username".to_string(),
-				RestrictedExpression::new_string(user_info.username),
-			),
-			("email".to_string(), exp_parsers::email_exp(&self.email)?),
-			(
-				"phone_number".to_string(),
-				RestrictedExpression::new_string(self.phone_number.clone()),
-			),
-			(
-				"role".to_string(),
-				RestrictedExpression::new_set(
-					user_info
-						.roles
-						.iter()
-						.map(|r| RestrictedExpression::new_string(r.to_owned())),
-				),
-			),
-		]);
-
-		let roles_entities = exp_parsers::roles_entities(user_info.roles);
-
-		let parents = HashSet::from_iter(roles_entities.iter().map(|e| e.uid()));
-		let user_entity = Entity::new(uid, attrs, parents)?;
-		let user_entry_uid = user_entity.uid();
-
-		let mut entities = roles_entities;
-		entities.push(user_entity);
-		Ok(UserInfoTokenEntityBox {
-			entities: entities,
-			user_entry_uid,
-		})
-	}
+/// User entity and entities of its roles.
+pub(crate) fn user_entities(
+	user: UserData,
+	shapes: &EntityShapes,
+	mapping: &ClaimMapping,
+) -> Result<UserEntityBox, EntityCreatingError> {
+	let type_name = "Jans::User";
+	let id = serde_json::json!({ "__entity": { "type": type_name, "id": user.user_id } });
+	let uid = EntityUid::from_json(id)
+		.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
+
+	// `role` is the list of the mapped roles
+	let mut claims = user.claims;
+	claims.insert("role".to_owned(), user.roles.into());
+	let attrs = shapes.entity_attrs(type_name, &claims.into(), mapping)?;
+
+	let roles_entities = exp_parsers::roles_entities(user.roles);
+
+	let parents = HashSet::from_iter(roles_entities.iter().map(|e| e.uid()));
+	let user_entity = Entity::new(uid, attrs, parents)?;
+	let user_entry_uid = user_entity.uid();
+
+	let mut entities = roles_entities;
+	entities.push(user_entity);
+	Ok(UserEntityBox {
+		entities,
+		user_entry_uid,
+	})
 }
 
 #[derive(serde::Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AccessToken {
 	pub aud: String,
-	pub exp: i64,
-	pub iat: i64,
 	pub iss: String,
 	pub jti: String,
-	pub scope: Vec<String>,
 	#[serde(rename = "client_id")]
 	pub client_id: String,
-	pub username: String,
-	// next fields don't used
-	// pub sub: String,
-	// pub code: String,
-	// #[serde(rename = "token_type")]
-	// pub token_type: String,
-	// pub acr: String,
-	// #[serde(rename = "x5t#S256")]
-	// pub x5t_s256: String,
-	// #[serde(rename = "auth_time")]
-	// pub auth_time: i64,
-	// pub status: Status,
+	#[serde(skip)]
+	pub claims: serde_json::Value,
 }
 
 pub struct AccessTokenEntityBox {
 	pub entities: Vec<Entity>,
 	pub client_entry_uid: EntityUid,
+	// created if the application name is configured
+	pub application_entry_uid: Option<EntityUid>,
 }
 
 impl AccessToken {
 	pub(crate) fn entities(
 		&self,
 		application_name: Option<&str>,
-	) -> Result<Vec<Entity>, EntityCreatingError> {
+		shapes: &EntityShapes,
+		mapping: &ClaimMapping,
+	) -> Result<AccessTokenEntityBox, EntityCreatingError> {
 		let mut box_entries = self.get_client_entity()?;
 		box_entries
 			.entities
-			.extend(self.get_access_token_entities()?);
+			.extend(self.get_access_token_entities(shapes, mapping)?);
 
 		if let Option::Some(name) = application_name {
-			box_entries
-				.entities
-				.push(self.get_application_entity(name, box_entries.client_entry_uid)?);
+			let application =
+				self.get_application_entity(name, box_entries.client_entry_uid.clone())?;
+			box_entries.application_entry_uid = Some(application.uid());
+			box_entries.entities.push(application);
 		}
 
-		Ok(box_entries.entities)
+		Ok(box_entries)
 	}
 
 	fn get_client_entity(&self) -> Result<AccessTokenEntityBox, EntityCreatingError> {
-		let id = serde_json::json!({ "__entity": { "type": "Jans::Client", "id": self.aud } });
+		// `aud` can be a resource server, the client which requested the token is `client_id`
+		let id =
+			serde_json::json!({ "__entity": { "type": "Jans::Client", "id": self.client_id } });
 		let id = EntityUid::from_json(id)
 			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
 
-		let trusted_issuer_entity = exp_parsers::trusted_issuer_entity(&self.iss)?;
+		let trusted_issuer_uid = exp_parsers::trusted_issuer_uid(&self.iss)?;
 
 		let parents = HashSet::new();
 		let attrs = HashMap::from([
@@ -381,13 +202,14 @@ impl AccessToken {
 			),
 			(
 				"iss".to_string(),
-				RestrictedExpression::new_entity_uid(trusted_issuer_entity.uid()),
+				RestrictedExpression::new_entity_uid(trusted_issuer_uid),
 			),
 		]);
 
 		let client_entity = Entity::new(id, attrs, parents)?;
 		Ok(AccessTokenEntityBox {
 			client_entry_uid: client_entity.uid(),
+			application_entry_uid: None,
 			entities: vec![client_entity],
 		})
 	}
@@ -416,42 +238,48 @@ impl AccessToken {
 		Ok(Entity::new(id, attrs, parents)?)
 	}
 
-	fn get_access_token_entities(&self) -> Result<Vec<Entity>, EntityCreatingError> {
-		let id =
-			serde_json::json!({ "__entity": { "type": "Jans::Access_token", "id": self.aud } });
+	fn get_access_token_entities(
+		&self,
+		shapes: &EntityShapes,
+		mapping: &ClaimMapping,
+	) -> Result<Vec<Entity>, EntityCreatingError> {
+		let type_name = "Jans::Access_token";
+		let id = serde_json::json!({ "__entity": { "type": type_name, "id": self.jti } });
 		let id = EntityUid::from_json(id)
 			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
 
-		let trusted_issuer_entity = exp_parsers::trusted_issuer_entity(&self.iss)?;
-
 		let parents = HashSet::new();
-		let attrs = HashMap::from([
-			(
-				"aud".to_owned(),
-				RestrictedExpression::new_string(self.aud.to_owned()),
-			),
-			("exp".to_owned(), RestrictedExpression::new_long(self.exp)),
-			("iat".to_owned(), RestrictedExpression::new_long(self.iat)),
-			(
-				"iss".to_owned(),
-				RestrictedExpression::new_entity_uid(trusted_issuer_entity.uid()),
-			),
-			(
-				"jti".to_owned(),
-				RestrictedExpression::new_string(self.jti.to_owned()),
-			),
-			("iat".to_owned(), RestrictedExpression::new_long(self.iat)),
-			(
-				"scope".to_owned(),
-				RestrictedExpression::new_set(
-					self.scope
-						.iter()
-						.map(|s| RestrictedExpression::new_string(s.to_owned())),
-				),
-			),
-		]);
+		let attrs = shapes.entity_attrs(type_name, &self.claims, mapping)?;
 
 		let access_token_entity = Entity::new(id, attrs, parents)?;
 		Ok(vec![access_token_entity])
 	}
 }
+
+/// Transaction token of the service chain, see OAuth Transaction Tokens draft.
+#[derive(serde::Deserialize, Debug)]
+pub struct TransactionToken {
+	pub iss: String,
+	// unique id of the transaction
+	pub txn: String,
+	#[serde(skip)]
+	pub claims: serde_json::Value,
+}
+
+impl TransactionToken {
+	pub(crate) fn entities(
+		&self,
+		shapes: &EntityShapes,
+		mapping: &ClaimMapping,
+	) -> Result<Vec<Entity>, EntityCreatingError> {
+		let type_name = "Jans::Transaction_token";
+		let id = serde_json::json!({ "__entity": { "type": type_name, "id": self.txn } });
+		let uid = EntityUid::from_json(id)
+			.map_err(|err| EntityCreatingError::CreateFromJson(err.to_string()))?;
+
+		let attrs = shapes.entity_attrs(type_name, &self.claims, mapping)?;
+
+		let tx_token_entity = Entity::new(uid, attrs, HashSet::with_capacity(0))?;
+		Ok(vec![tx_token_entity])
+	}
+}
@@ -1,96 +1,363 @@
-use base64::prelude::*;
-use cedar_policy::Policy;
+use cedar_policy::{EntityUid, Policy, PolicyId, SlotId, Template};
 use serde::de::Error as SerdeError;
-use std::collections::BTreeMap;
+use std::{
+	collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+	str::FromStr,
+};
+
+use super::claim_mapping::ClaimMapping;
+use super::entity_shapes::EntityShapes;
+use super::jwt_data_handler::TokenKind;
+use super::source::{Source, SourceContent};
 
 pub(crate) type TrustedIssuers = BTreeMap<String, TrustedIssuer>;
+pub(crate) type PolicyStores = BTreeMap<String, PolicyStoreEntry>;
 
-#[derive(Debug, serde::Deserialize)]
+/// Id of the policy store in the document which contains single policy store.
+pub const DEFAULT_POLICY_STORE_ID: &str = "default";
+
+// the document is either single policy store or several ones keyed by id:
+// `{ "policyStores": { "<id>": { "schema": .., "trustedIssuers": .., "policies": .. } } }`
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PolicyStoresDocument {
+	#[serde(deserialize_with = "parse_store_entries")]
+	policy_stores: PolicyStores,
+}
+
+#[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct DocumentKind {
+	policy_stores: Option<serde::de::IgnoredAny>,
+}
+
+/// Parse policy store document with one or several policy stores.
+pub(crate) fn parse_policy_stores(raw: &str) -> Result<PolicyStores, serde_json::Error> {
+	// kind of the document is checked first to keep errors of the policy store itself
+	if serde_json::from_str::<DocumentKind>(raw)?
+		.policy_stores
+		.is_some()
+	{
+		return Ok(serde_json::from_str::<PolicyStoresDocument>(raw)?.policy_stores);
+	}
+
+	let store: PolicyStoreEntry = serde_json::from_str(raw)?;
+	Ok(PolicyStores::from([(
+		DEFAULT_POLICY_STORE_ID.to_owned(),
+		store,
+	)]))
+}
+
+// policy stores keyed by id, repeated id is reported like repeated policy id
+fn parse_store_entries<'de, D>(deserializer: D) -> Result<PolicyStores, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	let stores = <Entries as serde::Deserialize>::deserialize(deserializer)?.parse(
+		"policy store",
+		|id, entry| {
+			let store = serde_json::from_value(entry).map_err(|err| err.to_string())?;
+			Ok((id, store))
+		},
+	)?;
+	Ok(stores.into_iter().collect())
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(try_from = "PolicyStoreRaw")]
 pub struct PolicyStoreEntry {
-	#[serde(deserialize_with = "parse_schema")]
 	pub schema: cedar_policy::Schema,
 	pub trusted_issuers: TrustedIssuers,
-	#[serde(deserialize_with = "parse_policies")]
+	// static policies, templates and template-linked policies
 	pub policies: cedar_policy::PolicySet,
+	// entities known in advance, they are added to the entities of every request
+	pub entities: cedar_policy::Entities,
+	// attributes of the entity types, entities of the tokens are built from them
+	pub entity_shapes: EntityShapes,
 }
 
-fn parse_schema<'de, D>(deserializer: D) -> Result<cedar_policy::Schema, D::Error>
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PolicyStoreRaw {
+	#[serde(deserialize_with = "parse_schema")]
+	schema: StoreSchema,
+	trusted_issuers: TrustedIssuers,
+	#[serde(deserialize_with = "parse_policies")]
+	policies: Vec<Policy>,
+	#[serde(default, deserialize_with = "parse_templates")]
+	templates: Vec<Template>,
+	#[serde(default)]
+	template_links: Vec<TemplateLink>,
+	// entities in Cedar JSON format
+	#[serde(default)]
+	entities: Option<serde_json::Value>,
+}
+
+/// Policy created from the template by filling its slots.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TemplateLink {
+	template_id: String,
+	new_id: String,
+	// entity uid keyed by slot: `?principal`, `?resource`,
+	// uid is written as string `Jans::User::"alice"` or object `{ "type": "Jans::User", "id": "alice" }`
+	values: BTreeMap<String, serde_json::Value>,
+}
+
+impl TemplateLink {
+	fn slot_values(&self) -> Result<HashMap<SlotId, EntityUid>, String> {
+		self.values
+			.iter()
+			.map(|(slot, value)| {
+				let slot_id = match slot.as_str() {
+					"?principal" => SlotId::principal(),
+					"?resource" => SlotId::resource(),
+					_ => return Err(format!("unknown slot {:?}", slot)),
+				};
+				let uid = match value {
+					serde_json::Value::String(uid) => EntityUid::from_str(uid).map_err(|err| {
+						format!("unable to parse entity uid of slot {}: {}", slot, err)
+					})?,
+					value => EntityUid::from_json(value.clone()).map_err(|err| {
+						format!("unable to parse entity uid of slot {}: {}", slot, err)
+					})?,
+				};
+				Ok((slot_id, uid))
+			})
+			.collect()
+	}
+}
+
+// static policies, templates and template links share the same policy ids,
+// keeps the entry which uses the id to name both entries on collision
+#[derive(Default)]
+struct PolicyIds(HashMap<String, String>);
+
+impl PolicyIds {
+	fn insert(&mut self, id: &str, entry: String) -> Result<(), String> {
+		match self.0.entry(id.to_owned()) {
+			Entry::Occupied(used) => Err(format!(
+				"policy id {:?} of {} is already used by {}",
+				id,
+				entry,
+				used.get()
+			)),
+			Entry::Vacant(vacant) => {
+				vacant.insert(entry);
+				Ok(())
+			}
+		}
+	}
+}
+
+impl TryFrom<PolicyStoreRaw> for PolicyStoreEntry {
+	type Error = String;
+
+	fn try_from(raw: PolicyStoreRaw) -> Result<Self, Self::Error> {
+		let mut ids = PolicyIds::default();
+		let mut policies = cedar_policy::PolicySet::new();
+
+		for policy in raw.policies {
+			let id = policy.id().to_string();
+			ids.insert(&id, format!("policy {:?}", id))?;
+			policies
+				.add(policy)
+				.map_err(|err| format!("unable to add policy {:?}: {}", id, err))?;
+		}
+
+		for template in raw.templates {
+			let id = template.id().to_string();
+			ids.insert(&id, format!("template {:?}", id))?;
+			policies
+				.add_template(template)
+				.map_err(|err| format!("unable to add template {:?}: {}", id, err))?;
+		}
+
+		// link is checked by cedar: the template exists and every its slot has a value
+		for link in raw.template_links {
+			ids.insert(
+				&link.new_id,
+				format!(
+					"template link {:?} of template {:?}",
+					link.new_id, link.template_id
+				),
+			)?;
+			let values = link
+				.slot_values()
+				.map_err(|err| format!("template link {:?}: {}", link.new_id, err))?;
+			policies
+				.link(
+					PolicyId::new(&link.template_id),
+					PolicyId::new(&link.new_id),
+					values,
+				)
+				.map_err(|err| format!("template link {:?}: {}", link.new_id, err))?;
+		}
+
+		for (id, issuer) in &raw.trusted_issuers {
+			issuer
+				.check_claim_mapping(&raw.schema.entity_shapes)
+				.map_err(|err| format!("trusted issuer {:?}: {}", id, err))?;
+		}
+
+		let entities = match raw.entities {
+			Some(entities) => {
+				cedar_policy::Entities::from_json_value(entities, Some(&raw.schema.schema))
+					.map_err(|err| format!("unable to parse entities: {}", err))?
+			}
+			None => cedar_policy::Entities::empty(),
+		};
+
+		Ok(Self {
+			schema: raw.schema.schema,
+			trusted_issuers: raw.trusted_issuers,
+			policies,
+			entities,
+			entity_shapes: raw.schema.entity_shapes,
+		})
+	}
+}
+
+struct StoreSchema {
+	schema: cedar_policy::Schema,
+	entity_shapes: EntityShapes,
+}
+
+// schema in Human Readable format is converted to JSON format to read attributes of the entity types
+fn parse_schema<'de, D>(deserializer: D) -> Result<StoreSchema, D::Error>
 where
 	D: serde::Deserializer<'de>,
 {
-	let source = <String as serde::Deserialize>::deserialize(deserializer)?;
-	let decoded_result: Result<Vec<u8>, D::Error> =
-		BASE64_STANDARD.decode(source.as_str()).map_err(|err| {
-			serde::de::Error::custom(format!(
-				"unable to parse Schema source as valid base64: {}, data: {}",
-				err.to_string(),
-				&source,
-			))
-		});
-	let decoded = match decoded_result {
-		Ok(v) => v,
-		//if we failed try to decode with NO_PAD
-		Err(origin_err) => BASE64_STANDARD_NO_PAD
-			.decode(source.as_str())
-			.map_err(|_err| origin_err)?,
+	let entry = <serde_json::Value as serde::Deserialize>::deserialize(deserializer)?;
+	let content = Source::from_entry(entry, "Schema")
+		.and_then(|source| source.content("Schema"))
+		.map_err(SerdeError::custom)?;
+
+	let (format, json) = match content {
+		SourceContent::Cedar(text) => {
+			let format = "Human Readable cedar format";
+			let (fragment, warnings) = cedar_policy::SchemaFragment::from_cedarschema_str(&text)
+				.map_err(|err| {
+					SerdeError::custom(format!("unable to parse Schema in {}: {}", format, err))
+				})?;
+
+			for warning in warnings {
+				log::warn!("Schema Parser generated warning: {:?}", warning);
+			}
+
+			let json = fragment.to_json_value().map_err(|err| {
+				SerdeError::custom(format!("unable to parse Schema in {}: {}", format, err))
+			})?;
+			(format, json)
+		}
+		SourceContent::Json(json) => ("JSON format", json),
 	};
 
-	let (schema, warnings) = cedar_policy::Schema::from_cedarschema_file(decoded.as_slice())
-		.map_err(|err| {
-			serde::de::Error::custom(format!(
-				"unable to parse Schema in Human Readable cedar format: {}",
-				err.to_string()
-			))
-		})?;
+	let schema = cedar_policy::Schema::from_json_value(json.clone()).map_err(|err| {
+		SerdeError::custom(format!("unable to parse Schema in {}: {}", format, err))
+	})?;
+	let entity_shapes = EntityShapes::from_schema_json(&json).map_err(|err| {
+		SerdeError::custom(format!("unable to parse Schema in {}: {}", format, err))
+	})?;
 
-	for warning in warnings {
-		log::warn!("Schema Parser generated warning: {:?}", warning);
+	Ok(StoreSchema {
+		schema,
+		entity_shapes,
+	})
+}
+
+fn parse_policy(id: String, entry: serde_json::Value) -> Result<Policy, String> {
+	let content = Source::from_entry(entry, "Policy")?.content("Policy")?;
+	match content {
+		SourceContent::Cedar(policy_src) => Policy::parse(Some(id), policy_src)
+			.map_err(|err| format!("unable to parse Policy from string: {}", err)),
+		SourceContent::Json(json) => Policy::from_json(Some(PolicyId::new(id)), json)
+			.map_err(|err| format!("unable to parse Policy from JSON: {}", err)),
 	}
+}
 
-	Ok(schema)
+// entries of the `policyStores`, `policies` or `templates` section keyed by id,
+// unlike a map it keeps repeated keys of the JSON object, so they can be reported
+struct Entries(Vec<(String, serde_json::Value)>);
+
+impl<'de> serde::Deserialize<'de> for Entries {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		struct EntriesVisitor;
+
+		impl<'de> serde::de::Visitor<'de> for EntriesVisitor {
+			type Value = Entries;
+
+			fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+				f.write_str("object with entries keyed by id")
+			}
+
+			fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+			where
+				A: serde::de::MapAccess<'de>,
+			{
+				let mut entries = Vec::new();
+				while let Some(entry) = map.next_entry()? {
+					entries.push(entry);
+				}
+				Ok(Entries(entries))
+			}
+		}
+
+		deserializer.deserialize_map(EntriesVisitor)
+	}
 }
 
-fn parse_policies<'de, D>(deserializer: D) -> Result<cedar_policy::PolicySet, D::Error>
+impl Entries {
+	/// Parse every entry, `kind` is used in error messages: `policy store`, `policy`, `template`.
+	fn parse<T, E: SerdeError>(
+		self,
+		kind: &str,
+		parse: impl Fn(String, serde_json::Value) -> Result<T, String>,
+	) -> Result<Vec<T>, E> {
+		let mut ids = HashSet::new();
+		self.0
+			.into_iter()
+			.map(|(id, entry)| {
+				if !ids.insert(id.clone()) {
+					return Err(E::custom(format!("{} {:?} is defined twice", kind, id)));
+				}
+				parse(id.clone(), entry)
+					.map_err(|err| E::custom(format!("{} {:?}: {}", kind, id, err)))
+			})
+			.collect()
+	}
+}
+
+fn parse_policies<'de, D>(deserializer: D) -> Result<Vec<Policy>, D::Error>
 where
 	D: serde::Deserializer<'de>,
 {
-	let policies = <BTreeMap<String, String> as serde::Deserialize>::deserialize(deserializer)?;
+	<Entries as serde::Deserialize>::deserialize(deserializer)?.parse("policy", parse_policy)
+}
 
-	let policies = policies
-		.into_iter()
-		.map(|(id, s)| {
-			BASE64_STANDARD
-				.decode(s)
-				.map_err(|err| {
-					SerdeError::custom(format!(
-						"unable to parse Policy source as valid base64: {}",
-						err
-					))
-				})
-				.and_then(|decoded| {
-					String::from_utf8(decoded).map_err(|err| {
-						SerdeError::custom(format!(
-							"unable to convert decoded base64 to UTF-8 string: {}",
-							err
-						))
-					})
-				})
-				.and_then(|policy_src| {
-					Policy::parse(Some(id), policy_src).map_err(|err| {
-						SerdeError::custom(format!("unable to parse Policy from string: {}", err))
-					})
-				})
-		})
-		.collect::<Result<Vec<Policy>, D::Error>>()?;
+fn parse_template(id: String, entry: serde_json::Value) -> Result<Template, String> {
+	let content = Source::from_entry(entry, "Template")?.content("Template")?;
+	match content {
+		SourceContent::Cedar(template_src) => Template::parse(Some(id), template_src)
+			.map_err(|err| format!("unable to parse Template from string: {}", err)),
+		SourceContent::Json(json) => Template::from_json(Some(PolicyId::new(id)), json)
+			.map_err(|err| format!("unable to parse Template from JSON: {}", err)),
+	}
+}
 
-	Ok(cedar_policy::PolicySet::from_policies(policies).unwrap())
+// templates are written in the same way as policies
+fn parse_templates<'de, D>(deserializer: D) -> Result<Vec<Template>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	<Entries as serde::Deserialize>::deserialize(deserializer)?.parse("template", parse_template)
 }
 
-#[allow(dead_code)]
 #[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-
 pub struct TrustedIssuer {
 	pub name: Option<String>,
 	pub openid_configuration_endpoint: String,
@@ -98,27 +365,94 @@ pub struct TrustedIssuer {
 	pub access_tokens: AccessTokenConfig,
 	pub id_tokens: IdTokenConfig,
 	pub userinfo_tokens: UserInfoTokenConfig,
+	// transaction tokens are not trusted if the section is missing
+	#[serde(default)]
+	pub tx_tokens: TransactionTokenConfig,
+}
+
+impl TrustedIssuer {
+	/// Issuer identifier, OpenID configuration endpoint is `{issuer}/.well-known/openid-configuration`.
+	pub fn issuer(&self) -> &str {
+		let endpoint = self.openid_configuration_endpoint.as_str();
+		endpoint
+			.strip_suffix("/.well-known/openid-configuration")
+			.unwrap_or(endpoint)
+			.trim_end_matches('/')
+	}
+
+	/// Claim mapping rules of the token entities of `kind`,
+	/// `Jans::User` entity is created by the rules of userinfo_token.
+	pub fn claim_mapping(&self, kind: TokenKind) -> &ClaimMapping {
+		match kind {
+			TokenKind::IdToken => &self.id_tokens.claim_mapping,
+			TokenKind::UserinfoToken => &self.userinfo_tokens.claim_mapping,
+			TokenKind::AccessToken => &self.access_tokens.claim_mapping,
+			TokenKind::TransactionToken => &self.tx_tokens.claim_mapping,
+		}
+	}
+
+	// every rule should name an attribute of the entity created from the token,
+	// otherwise misspelled attribute would be silently skipped
+	fn check_claim_mapping(&self, shapes: &EntityShapes) -> Result<(), String> {
+		for kind in TokenKind::ALL {
+			let types = kind.mapped_entity_types();
+			for name in self.claim_mapping(kind).keys() {
+				if !types
+					.iter()
+					.any(|type_name| shapes.has_attr(type_name, name))
+				{
+					return Err(format!(
+						"claim mapping of {} has rule for {:?}, which is not an attribute of {}",
+						kind,
+						name,
+						types.join(" or ")
+					));
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Find trusted issuer by `iss` claim of the token.
+pub(crate) fn find_trusted_issuer<'a>(
+	issuers: &'a TrustedIssuers,
+	iss: &str,
+) -> Option<&'a TrustedIssuer> {
+	let iss = iss.trim_end_matches('/');
+	issuers.values().find(|issuer| issuer.issuer() == iss)
 }
 
-#[allow(dead_code)]
 #[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AccessTokenConfig {
 	pub trusted: bool,
+	#[serde(default)]
+	pub claim_mapping: ClaimMapping,
 }
 
-#[allow(dead_code)]
 #[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IdTokenConfig {
 	pub trusted: bool,
 	pub principal_identifier: Option<String>,
+	#[serde(default)]
+	pub claim_mapping: ClaimMapping,
 }
 
-#[allow(dead_code)]
 #[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UserInfoTokenConfig {
 	pub trusted: bool,
 	pub role_mapping: Option<String>,
+	#[serde(default)]
+	pub claim_mapping: ClaimMapping,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionTokenConfig {
+	pub trusted: bool,
+	#[serde(default)]
+	pub claim_mapping: ClaimMapping,
 }
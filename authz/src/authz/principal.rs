@@ -0,0 +1,82 @@
+use cedar_policy::{Decision, Response};
+
+/// Which principals are authorized by the request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrincipalMode {
+	/// `Jans::User` if id_token or userinfo_token is given, otherwise the workload
+	#[default]
+	Auto,
+	/// `Jans::User` created from id_token and userinfo_token
+	User,
+	/// `Jans::Client` or `Jans::Application` created from the access_token
+	Workload,
+	/// Both the user and the workload are authorized, their decisions are combined by the rule
+	Combined(DecisionRule),
+}
+
+/// How decisions of the user and the workload are combined in [`PrincipalMode::Combined`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionRule {
+	/// Allowed if both principals are allowed
+	And,
+	/// Allowed if any of the principals is allowed
+	Or,
+}
+
+impl DecisionRule {
+	fn combine(self, a: Decision, b: Decision) -> Decision {
+		let allowed = match self {
+			Self::And => a == Decision::Allow && b == Decision::Allow,
+			Self::Or => a == Decision::Allow || b == Decision::Allow,
+		};
+		if allowed {
+			Decision::Allow
+		} else {
+			Decision::Deny
+		}
+	}
+}
+
+/// Principal of the request created from the tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PrincipalKind {
+	User,
+	Workload,
+}
+
+/// Result of the authorization request with the responses of every authorized principal.
+#[derive(Debug, Clone)]
+pub struct AuthzResponse {
+	decision: Decision,
+	/// Response for `Jans::User` principal if it is authorized
+	pub user: Option<Response>,
+	/// Response for `Jans::Client` or `Jans::Application` principal if it is authorized
+	pub workload: Option<Response>,
+}
+
+impl AuthzResponse {
+	pub(crate) fn new(
+		rule: Option<DecisionRule>,
+		user: Option<Response>,
+		workload: Option<Response>,
+	) -> Self {
+		let decision = match (&user, &workload) {
+			(Some(user), Some(workload)) => rule
+				.unwrap_or(DecisionRule::And)
+				.combine(user.decision(), workload.decision()),
+			(Some(response), None) | (None, Some(response)) => response.decision(),
+			(None, None) => Decision::Deny,
+		};
+
+		Self {
+			decision,
+			user,
+			workload,
+		}
+	}
+
+	/// Decision of the single principal or combined decision in [`PrincipalMode::Combined`] mode.
+	pub fn decision(&self) -> Decision {
+		self.decision
+	}
+}
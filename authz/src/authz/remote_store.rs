@@ -0,0 +1,177 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use jwt::{HttpClient, HttpError, HttpResponse, UreqClient};
+
+use super::policy_store::{parse_policy_stores, PolicyStores};
+
+/// Transport of the remote policy store strategy, [`UreqClient`] is used by default.
+#[derive(Clone)]
+pub struct RemoteTransport(pub Arc<dyn HttpClient>);
+
+impl Default for RemoteTransport {
+	fn default() -> Self {
+		Self(Arc::new(UreqClient::default()))
+	}
+}
+
+impl std::fmt::Debug for RemoteTransport {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("RemoteTransport")
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RemoteStoreError {
+	#[error("{0}")]
+	Transport(#[from] HttpError),
+	#[error("could not parse downloaded policy store: {0}")]
+	ParseJson(#[from] serde_json::Error),
+	#[error("could not parse cached policy store: {0}")]
+	ParseCache(serde_json::Error),
+	#[error("server responded not modified, but there is no cached policy store")]
+	MissingCache,
+}
+
+// last downloaded policy store kept on disk,
+// ETag is kept in the file near the document: `<path>.etag`
+struct Cache<'a> {
+	path: &'a Path,
+}
+
+impl Cache<'_> {
+	fn etag_path(&self) -> PathBuf {
+		let mut path = self.path.as_os_str().to_owned();
+		path.push(".etag");
+		path.into()
+	}
+
+	fn body(&self) -> Option<String> {
+		std::fs::read_to_string(self.path).ok()
+	}
+
+	fn etag(&self) -> Option<String> {
+		std::fs::read_to_string(self.etag_path()).ok()
+	}
+
+	fn store(&self, body: &str, etag: Option<&str>) -> std::io::Result<()> {
+		if let Some(dir) = self.path.parent() {
+			std::fs::create_dir_all(dir)?;
+		}
+		std::fs::write(self.path, body)?;
+		match etag {
+			Some(etag) => std::fs::write(self.etag_path(), etag),
+			None => match std::fs::remove_file(self.etag_path()) {
+				Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+				_ => Ok(()),
+			},
+		}
+	}
+}
+
+/// Downloaded policy store document which is not cached yet.
+pub(crate) struct RemoteDocument {
+	body: String,
+	etag: Option<String>,
+}
+
+impl RemoteDocument {
+	/// Save the document to the cache, it should be done only after its policy stores are accepted.
+	pub(crate) fn cache(&self, cache_path: &Path) {
+		let cache = Cache { path: cache_path };
+		if let Err(err) = cache.store(&self.body, self.etag.as_deref()) {
+			log::warn!("could not cache policy store to {cache_path:?}: {err}");
+		}
+	}
+}
+
+/// Downloaded policy stores with ETag of their version.
+pub(crate) struct RemotePolicy {
+	pub(crate) stores: PolicyStores,
+	pub(crate) etag: Option<String>,
+	// `None` if the policy stores are read from the cache
+	pub(crate) document: Option<RemoteDocument>,
+}
+
+/// Download policy store if it is changed since the version with `etag`,
+/// returns `None` if the server responds `304 Not Modified`.
+/// Downloaded document is returned with the policy stores and is not cached by this function.
+pub(crate) fn fetch_remote_policy(
+	url: &str,
+	transport: &dyn HttpClient,
+	etag: Option<&str>,
+) -> Result<Option<RemotePolicy>, RemoteStoreError> {
+	match transport.get_if_none_match(url, etag)? {
+		HttpResponse::NotModified => {
+			log::debug!("remote policy store {url} is not modified");
+			Ok(None)
+		}
+		HttpResponse::Modified { body, etag } => {
+			let stores = parse_policy_stores(&body)?;
+			Ok(Some(RemotePolicy {
+				stores,
+				etag: etag.clone(),
+				document: Some(RemoteDocument { body, etag }),
+			}))
+		}
+	}
+}
+
+/// Policy stores of the cached copy, `None` if there is no cached copy or it could not be parsed.
+pub(crate) fn cached_remote_policy(cache_path: &Path) -> Option<RemotePolicy> {
+	let cache = Cache { path: cache_path };
+	let stores = match parse_policy_stores(&cache.body()?) {
+		Ok(stores) => stores,
+		Err(err) => {
+			log::warn!("{}", RemoteStoreError::ParseCache(err));
+			return None;
+		}
+	};
+	Some(RemotePolicy {
+		stores,
+		etag: cache.etag(),
+		document: None,
+	})
+}
+
+/// Download policy store, cached copy is used if the server responds `304 Not Modified`
+/// or if the policy store could not be downloaded.
+pub(crate) fn get_remote_policy(
+	url: &str,
+	cache_path: &Path,
+	transport: &dyn HttpClient,
+) -> Result<RemotePolicy, RemoteStoreError> {
+	let cache = Cache { path: cache_path };
+	let cached_body = cache.body();
+	// ETag without the document is useless
+	let etag = cached_body.as_ref().and_then(|_| cache.etag());
+
+	let from_cache = |body: &str| {
+		Ok(RemotePolicy {
+			stores: parse_policy_stores(body).map_err(RemoteStoreError::ParseCache)?,
+			etag: etag.clone(),
+			document: None,
+		})
+	};
+
+	let fetched =
+		fetch_remote_policy(url, transport, etag.as_deref()).and_then(|policy| match policy {
+			Some(policy) => Ok(policy),
+			None => from_cache(
+				cached_body
+					.as_deref()
+					.ok_or(RemoteStoreError::MissingCache)?,
+			),
+		});
+
+	match (fetched, cached_body.as_deref()) {
+		(Ok(policy), _) => Ok(policy),
+		(Err(err), Some(body)) => {
+			log::warn!("could not update remote policy store, cached copy is used: {err}");
+			from_cache(body)
+		}
+		(Err(err), None) => Err(err),
+	}
+}
@@ -0,0 +1,89 @@
+use base64::prelude::*;
+
+/// Format of the policy or schema source.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+	/// Cedar language: policies or human readable `.cedarschema`
+	#[default]
+	Cedar,
+	/// Cedar JSON policy or schema format
+	Json,
+}
+
+/// Encoding of the policy or schema source.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceEncoding {
+	/// Source is written as is, JSON source can be inline object
+	#[default]
+	None,
+	Base64,
+}
+
+// entry of the policy store written as object:
+// `{ "format": "cedar" | "json", "encoding": "none" | "base64", "body": .. }`,
+// string entry is base64 encoded source in cedar format
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct Source {
+	#[serde(default)]
+	format: SourceFormat,
+	#[serde(default)]
+	encoding: SourceEncoding,
+	body: serde_json::Value,
+}
+
+pub(crate) enum SourceContent {
+	Cedar(String),
+	Json(serde_json::Value),
+}
+
+impl Source {
+	/// Read the entry, `kind` is used in error messages: `Policy`, `Schema`.
+	pub(crate) fn from_entry(entry: serde_json::Value, kind: &str) -> Result<Self, String> {
+		match entry {
+			serde_json::Value::String(_) => Ok(Self {
+				format: SourceFormat::Cedar,
+				encoding: SourceEncoding::Base64,
+				body: entry,
+			}),
+			entry => serde_json::from_value(entry)
+				.map_err(|err| format!("unable to parse {} source entry: {}", kind, err)),
+		}
+	}
+
+	pub(crate) fn content(self, kind: &str) -> Result<SourceContent, String> {
+		let text = match (self.encoding, self.body) {
+			(SourceEncoding::Base64, serde_json::Value::String(body)) => {
+				let decoded = BASE64_STANDARD
+					.decode(body.as_str())
+					//if we failed try to decode with NO_PAD
+					.or_else(|err| {
+						BASE64_STANDARD_NO_PAD
+							.decode(body.as_str())
+							.map_err(|_| err)
+					})
+					.map_err(|err| {
+						format!("unable to parse {} source as valid base64: {}", kind, err)
+					})?;
+				String::from_utf8(decoded).map_err(|err| {
+					format!("unable to convert decoded base64 to UTF-8 string: {}", err)
+				})?
+			}
+			(SourceEncoding::None, serde_json::Value::String(body)) => body,
+			// inline JSON source
+			(SourceEncoding::None, body) if matches!(self.format, SourceFormat::Json) => {
+				return Ok(SourceContent::Json(body))
+			}
+			(_, body) => return Err(format!("{} source body should be string: {}", kind, body)),
+		};
+
+		match self.format {
+			SourceFormat::Cedar => Ok(SourceContent::Cedar(text)),
+			SourceFormat::Json => serde_json::from_str(&text)
+				.map(SourceContent::Json)
+				.map_err(|err| format!("unable to parse {} source as JSON: {}", kind, err)),
+		}
+	}
+}
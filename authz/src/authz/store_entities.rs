@@ -0,0 +1,43 @@
+use std::collections::HashSet;
+
+use cedar_policy::{Entities, Entity, EntityUid};
+
+/// What to do when an entity created from the tokens has the same uid
+/// as an entity from the `entities` section of the policy store.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EntityConflictMode {
+	/// Request is rejected with [`super::HandleError::EntityConflict`]
+	#[default]
+	Reject,
+	/// Entity of the policy store is used
+	PreferStore,
+	/// Entity created from the tokens is used
+	PreferToken,
+}
+
+/// Add entities of the policy store to the entities created from the tokens.
+/// Uid of the conflicting entity is returned in [`EntityConflictMode::Reject`] mode.
+pub(crate) fn merge_entities(
+	token_entities: Vec<Entity>,
+	store_entities: &Entities,
+	mode: EntityConflictMode,
+) -> Result<Vec<Entity>, EntityUid> {
+	let token_uids: HashSet<EntityUid> = token_entities.iter().map(Entity::uid).collect();
+
+	let mut list = Vec::new();
+	for entity in store_entities.iter() {
+		if token_uids.contains(&entity.uid()) {
+			match mode {
+				EntityConflictMode::Reject => return Err(entity.uid()),
+				EntityConflictMode::PreferToken => continue,
+				EntityConflictMode::PreferStore => {}
+			}
+		}
+		list.push(entity.clone());
+	}
+
+	list.extend(token_entities.into_iter().filter(|entity| {
+		mode != EntityConflictMode::PreferStore || store_entities.get(&entity.uid()).is_none()
+	}));
+	Ok(list)
+}
@@ -0,0 +1,93 @@
+use cedar_policy::{SourceLocation, ValidationMode, Validator};
+
+use super::policy_store::PolicyStores;
+
+/// How to handle warnings of the policy validation, errors are always fatal.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ValidationWarningMode {
+	/// Warnings are logged and the policy store is used
+	#[default]
+	Log,
+	/// Policy store with warnings is rejected
+	Deny,
+}
+
+/// Problem found by the validator in the policy.
+#[derive(Debug, Clone)]
+pub struct PolicyIssue {
+	pub policy_id: String,
+	/// Byte offsets of the problem in the policy source,
+	/// `None` for policies written in JSON format.
+	pub location: Option<(usize, usize)>,
+	pub message: String,
+}
+
+impl PolicyIssue {
+	fn new(location: &SourceLocation, message: String) -> Self {
+		Self {
+			policy_id: location.policy_id().to_string(),
+			location: location.range_start().zip(location.range_end()),
+			message,
+		}
+	}
+}
+
+impl std::fmt::Display for PolicyIssue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "policy {:?}", self.policy_id)?;
+		if let Some((start, end)) = self.location {
+			write!(f, " at offset {start}-{end}")?;
+		}
+		write!(f, ": {}", self.message)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("policies of the policy store {store_id:?} are not valid: {}", display_issues(.issues))]
+pub struct PolicyValidationError {
+	pub store_id: String,
+	/// Validation errors, and warnings if they are denied.
+	pub issues: Vec<PolicyIssue>,
+}
+
+fn display_issues(issues: &[PolicyIssue]) -> String {
+	issues
+		.iter()
+		.map(PolicyIssue::to_string)
+		.collect::<Vec<_>>()
+		.join("; ")
+}
+
+/// Validate policies of every policy store against its schema in strict mode.
+pub(crate) fn validate_stores(
+	stores: &PolicyStores,
+	warning_mode: ValidationWarningMode,
+) -> Result<(), PolicyValidationError> {
+	for (store_id, store) in stores {
+		let result =
+			Validator::new(store.schema.clone()).validate(&store.policies, ValidationMode::Strict);
+
+		let mut issues: Vec<PolicyIssue> = result
+			.validation_errors()
+			.map(|err| PolicyIssue::new(err.location(), err.error_kind().to_string()))
+			.collect();
+
+		for warning in result.validation_warnings() {
+			let issue = PolicyIssue::new(warning.location(), warning.warning_kind().to_string());
+			match warning_mode {
+				ValidationWarningMode::Log => {
+					log::warn!("policy store {store_id:?} validation warning: {issue}")
+				}
+				ValidationWarningMode::Deny => issues.push(issue),
+			}
+		}
+
+		if !issues.is_empty() {
+			return Err(PolicyValidationError {
+				store_id: store_id.to_owned(),
+				issues,
+			});
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,58 @@
+use std::{
+	sync::{
+		mpsc::{self, RecvTimeoutError},
+		Arc,
+	},
+	thread::JoinHandle,
+	time::Duration,
+};
+
+use super::Authz;
+
+/// Background thread that reloads the policy store when it is changed, see [`Authz::watch`].
+///
+/// The thread is stopped when the watcher is dropped or when [`Authz`] is dropped.
+pub struct PolicyStoreWatcher {
+	stop: Option<mpsc::Sender<()>>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for PolicyStoreWatcher {
+	fn drop(&mut self) {
+		// closed channel wakes up the thread
+		self.stop.take();
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Authz {
+	/// Check every `interval` if the policy store is changed and reload it,
+	/// see [`Authz::reload_if_changed`].
+	/// If the changed policy store could not be loaded the current one is kept.
+	pub fn watch(self: &Arc<Self>, interval: Duration) -> PolicyStoreWatcher {
+		let authz = Arc::downgrade(self);
+		let (stop, stopped) = mpsc::channel::<()>();
+
+		let thread = std::thread::spawn(move || {
+			while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+				let Some(authz) = authz.upgrade() else {
+					break;
+				};
+				match authz.reload_if_changed() {
+					Ok(true) => log::info!("policy store is reloaded"),
+					Ok(false) => {}
+					Err(err) => {
+						log::warn!("could not reload policy store, previous one is used: {err}")
+					}
+				}
+			}
+		});
+
+		PolicyStoreWatcher {
+			stop: Some(stop),
+			thread: Some(thread),
+		}
+	}
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use authz::{Authz, AuthzNewError, GetPolicyError, HandleError};
+use cedar_policy::Decision;
+use common::{Input, SCHEMA};
+use serde_json::{json, Value};
+
+// demo store where attributes are added to the `id_token` and `User` entity types
+// and the Gluu issuer has claim mapping rules of `kind` tokens
+fn mapped_authz(id_token_attrs: &str, user_attrs: &str, kind: &str, mapping: Value) -> Authz {
+	let schema = SCHEMA
+		.replacen(
+			"        sub: String,\n    };",
+			&format!("        sub: String,\n        {id_token_attrs}\n    }};"),
+			1,
+		)
+		.replacen(
+			"        role: Set<String>,",
+			&format!("        role: Set<String>,\n        {user_attrs}"),
+			1,
+		);
+
+	let mut store = common::store();
+	store["schema"] = json!({ "body": schema });
+	store["trustedIssuers"]["Gluu"][kind]["claimMapping"] = mapping;
+	store["policies"] = json!({
+		"admin": {
+			"body": "permit(principal is Jans::User, action, resource) when { principal has admin_roles && principal.admin_roles.contains(\"api-admin\") };"
+		}
+	});
+
+	Authz::new(common::config_with_store(&store)).unwrap()
+}
+
+#[test]
+fn claims_are_mapped_by_issuer_rules() {
+	let authz = mapped_authz(
+		"status_idx?: Long,",
+		"admin_roles?: Set<String>,",
+		"userinfoTokens",
+		json!({ "admin_roles": { "claim": "jansAdminUIRole" } }),
+	);
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+
+	let authz = mapped_authz(
+		"status_idx?: Long,",
+		"admin_roles?: Set<String>,",
+		"idTokens",
+		json!({ "status_idx": { "claim": "status.status_list.idx" } }),
+	);
+	let entities = Input::demo().entities(&authz).unwrap();
+	let id_token = common::entity_json(&entities, "Jans::id_token");
+	assert_eq!(id_token["attrs"]["status_idx"], 2004);
+
+	// rules of the id_token are not used for the user entity
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert_eq!(result.decision(), Decision::Deny);
+}
+
+#[test]
+fn claims_are_parsed_by_builtin_parsers() {
+	let authz = mapped_authz(
+		"contact: email_address, issuer_url: Url, network: ipaddr, score: decimal, updated: Long, logged_in: Long,",
+		"",
+		"idTokens",
+		json!({
+			"contact": { "claim": "email", "parser": "email" },
+			"issuer_url": { "claim": "iss", "parser": "url" },
+			"network": { "claim": "ip", "parser": "ip" },
+			"score": { "parser": "decimal" },
+			"updated": { "claim": "updated", "parser": "datetime" },
+			"logged_in": { "claim": "auth_time", "parser": "datetime" },
+		}),
+	);
+
+	let mut input = Input::demo();
+	input.id_token["ip"] = "10.0.0.1".into();
+	input.id_token["score"] = json!(0.75);
+	input.id_token["updated"] = "2024-08-30T11:55:02Z".into();
+
+	let entities = input.entities(&authz).unwrap();
+	let attrs = common::entity_json(&entities, "Jans::id_token")["attrs"].clone();
+	assert_eq!(
+		attrs["contact"],
+		json!({ "id": "admin", "domain": "admin-ui-test.gluu.org" })
+	);
+	assert_eq!(attrs["issuer_url"]["host"], "admin-ui-test.gluu.org");
+	assert_eq!(attrs["network"]["__extn"]["arg"], "10.0.0.1");
+	assert_eq!(attrs["score"]["__extn"]["arg"], "0.75");
+	assert_eq!(attrs["updated"], 1725018902);
+	assert_eq!(attrs["logged_in"], 1725018931);
+
+	input.id_token["updated"] = "yesterday".into();
+	let err = match input.entities(&authz) {
+		Err(err @ HandleError::AuthzInputEntities(_)) => err.to_string(),
+		result => panic!("{:?}", result.map(|_| ())),
+	};
+	assert!(err.contains("claim `updated`"), "{err}");
+}
+
+#[test]
+fn rule_of_undeclared_attribute_is_rejected() {
+	let mut store = common::store();
+	store["trustedIssuers"]["Gluu"]["idTokens"]["claimMapping"] =
+		json!({ "nonexistent": { "claim": "sub" } });
+
+	let result = Authz::new(common::config_with_store(&store));
+	assert!(
+		matches!(
+			&result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+				if err.to_string().contains(r#"claim mapping of id_token has rule for "nonexistent""#)
+		),
+		"{:?}",
+		result.err()
+	);
+}
@@ -0,0 +1,157 @@
+#![allow(dead_code)]
+
+use authz::{jwt, Authz, AuthzConfig, PolicyStoreConfig};
+use base64::prelude::*;
+use serde_json::Value;
+
+pub const INPUT: &str = include_str!("../../../cedar_files/input.json");
+pub const STORE: &str = include_str!("../../../policy-store/local.json");
+pub const SCHEMA: &str = include_str!("../../../cedar_files/cedarling_demo_schema.schema");
+/// Id of the demo application: resource of the demo input and `client_id` of its access_token.
+pub const APP_ID: &str = "33d8c020-5c91-4fa6-8041-484eaae39926";
+
+/// Demo input with claims of tokens that can be changed before encoding.
+pub struct Input {
+	pub id_token: Value,
+	pub userinfo_token: Value,
+	pub access_token: Value,
+	pub tx_token: Option<Value>,
+	// other fields of input json (action, resource, context)
+	pub extra: Value,
+}
+
+fn decode_claims(jwt: &str) -> Value {
+	let payload = jwt.split('.').nth(1).unwrap();
+	serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+}
+
+/// Encode claims to unsigned JWT, can be decoded only without validation.
+pub fn unsigned_jwt(claims: &Value) -> String {
+	let header = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+	let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+	format!("{header}.{payload}.")
+}
+
+impl Input {
+	pub fn demo() -> Self {
+		let mut extra: Value = serde_json::from_str(INPUT).unwrap();
+		let mut take = |name: &str| decode_claims(extra[name].take().as_str().unwrap());
+		Self {
+			id_token: take("id_token"),
+			userinfo_token: take("userinfo_token"),
+			access_token: take("access_token"),
+			tx_token: Some(take("tx_token")),
+			extra,
+		}
+	}
+
+	pub fn to_json(&self) -> String {
+		let mut input = self.extra.clone();
+		input["id_token"] = unsigned_jwt(&self.id_token).into();
+		input["userinfo_token"] = unsigned_jwt(&self.userinfo_token).into();
+		input["access_token"] = unsigned_jwt(&self.access_token).into();
+		if let Some(tx_token) = &self.tx_token {
+			input["tx_token"] = unsigned_jwt(tx_token).into();
+		}
+		input.to_string()
+	}
+
+	/// Input json without `tokens`.
+	pub fn to_json_without(&self, tokens: &[&str]) -> String {
+		let mut input: Value = serde_json::from_str(&self.to_json()).unwrap();
+		for token in tokens {
+			input.as_object_mut().unwrap().remove(*token);
+		}
+		input.to_string()
+	}
+}
+
+pub fn config() -> AuthzConfig {
+	AuthzConfig::new(
+		Some("Demo_App".to_owned()),
+		jwt::JWTDecoder::new_without_validation(),
+		PolicyStoreConfig::Local,
+	)
+}
+
+pub fn authz() -> Authz {
+	Authz::new(config()).unwrap()
+}
+
+/// Demo policy store json, can be changed before it is used by [`config_with_store`].
+pub fn store() -> Value {
+	serde_json::from_str(STORE).unwrap()
+}
+
+pub fn config_with_store(store: &Value) -> AuthzConfig {
+	AuthzConfig {
+		policy: PolicyStoreConfig::Json {
+			value: store.to_string(),
+		},
+		..config()
+	}
+}
+
+/// Demo config where `policy` is added to the policy store
+/// and principal types of the `Execute` action are replaced by `principals`.
+pub fn config_with(policy: Option<&str>, principals: Option<&str>) -> AuthzConfig {
+	let mut store = store();
+	if let Some(policy) = policy {
+		store["policies"]["extra"] = serde_json::json!({ "body": policy });
+	}
+	if let Some(principals) = principals {
+		let schema = SCHEMA.replacen(
+			"principal: [User, Role, Client],",
+			&format!("principal: [{principals}],"),
+			1,
+		);
+		store["schema"] = serde_json::json!({ "body": schema });
+	}
+	config_with_store(&store)
+}
+
+impl Input {
+	/// Set `iss` claim of id_token, userinfo_token and access_token, tx_token is kept.
+	pub fn with_issuer(mut self, iss: &str) -> Self {
+		for token in [
+			&mut self.id_token,
+			&mut self.userinfo_token,
+			&mut self.access_token,
+		] {
+			token["iss"] = iss.into();
+		}
+		self
+	}
+
+	/// Entities created from the input by `authz`.
+	pub fn entities(&self, authz: &Authz) -> Result<cedar_policy::Entities, authz::HandleError> {
+		let input = authz::AuthzInputRaw::parse_raw(&self.to_json()).unwrap();
+		let decoded = input
+			.decode_tokens(&jwt::JWTDecoder::new_without_validation())
+			.unwrap();
+		authz
+			.get_entities(decoded.jwt)
+			.map(|entities| entities.entities)
+	}
+}
+
+/// Uid of the user entity in `entities`.
+pub fn user_uid(entities: &cedar_policy::Entities) -> cedar_policy::EntityUid {
+	let users: Vec<_> = entities
+		.iter()
+		.filter(|e| e.uid().type_name().to_string() == "Jans::User")
+		.map(|e| e.uid())
+		.collect();
+	assert_eq!(users.len(), 1, "{users:?}");
+	users[0].clone()
+}
+
+/// Json of the single entity with `type_name` in `entities`.
+pub fn entity_json(entities: &cedar_policy::Entities, type_name: &str) -> Value {
+	let list: Vec<_> = entities
+		.iter()
+		.filter(|e| e.uid().type_name().to_string() == type_name)
+		.collect();
+	assert_eq!(list.len(), 1, "{type_name}: {list:?}");
+	list[0].to_json_value().unwrap()
+}
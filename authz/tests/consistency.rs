@@ -0,0 +1,105 @@
+mod common;
+
+use authz::{Authz, AuthzInputEntitiesError as Error, HandleError, TokensConsistencyConfig};
+use cedar_policy::Decision;
+use common::Input;
+
+fn mismatch_error(input: &Input, consistency: TokensConsistencyConfig) -> Option<Error> {
+	let mut config = common::config();
+	config.consistency = consistency;
+	match Authz::new(config)
+		.unwrap()
+		.handle_raw_input(&input.to_json())
+	{
+		Ok(response) => {
+			assert_eq!(response.decision(), Decision::Allow);
+			None
+		}
+		Err(HandleError::AuthzInputEntities(err)) => Some(err),
+		Err(err) => panic!("unexpected error: {err}"),
+	}
+}
+
+const DISABLED: TokensConsistencyConfig = TokensConsistencyConfig {
+	id_token_audience: false,
+	same_issuer: false,
+	userinfo_subject: false,
+	userinfo_issuer: false,
+};
+
+#[test]
+fn consistent_tokens_are_accepted() {
+	let result = mismatch_error(&Input::demo(), TokensConsistencyConfig::default());
+	assert!(result.is_none(), "{result:?}");
+}
+
+#[test]
+fn id_token_for_other_client_is_rejected() {
+	let mut input = Input::demo();
+	input.id_token["aud"] = "other-client".into();
+
+	let err = mismatch_error(
+		&input,
+		TokensConsistencyConfig {
+			id_token_audience: true,
+			..DISABLED
+		},
+	)
+	.unwrap();
+	assert!(
+		matches!(err, Error::IdTokenAudienceMismatch { .. }),
+		"{err}"
+	);
+	assert!(mismatch_error(&input, DISABLED).is_none());
+}
+
+#[test]
+fn tokens_of_different_issuers_are_rejected() {
+	let mut input = Input::demo();
+	input.access_token["iss"] = "https://accounts.google.com".into();
+
+	let err = mismatch_error(
+		&input,
+		TokensConsistencyConfig {
+			same_issuer: true,
+			..DISABLED
+		},
+	)
+	.unwrap();
+	assert!(matches!(err, Error::IssuerMismatch { .. }), "{err}");
+	assert!(mismatch_error(&input, DISABLED).is_none());
+}
+
+#[test]
+fn userinfo_of_other_user_or_issuer_is_rejected() {
+	let mut input = Input::demo();
+	input.userinfo_token["sub"] = "other-user".into();
+
+	let err = mismatch_error(
+		&input,
+		TokensConsistencyConfig {
+			userinfo_subject: true,
+			..DISABLED
+		},
+	)
+	.unwrap();
+	assert!(
+		matches!(err, Error::UserInfoSubjectMismatch { .. }),
+		"{err}"
+	);
+	assert!(mismatch_error(&input, DISABLED).is_none());
+
+	let mut input = Input::demo();
+	input.userinfo_token["iss"] = "https://accounts.google.com".into();
+
+	let err = mismatch_error(
+		&input,
+		TokensConsistencyConfig {
+			userinfo_issuer: true,
+			..DISABLED
+		},
+	)
+	.unwrap();
+	assert!(matches!(err, Error::UserInfoIssuerMismatch { .. }), "{err}");
+	assert!(mismatch_error(&input, DISABLED).is_none());
+}
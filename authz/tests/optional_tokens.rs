@@ -0,0 +1,109 @@
+mod common;
+
+use authz::{Authz, HandleError, UntrustedTokenMode};
+use cedar_policy::Decision;
+use common::{Input, APP_ID};
+
+#[test]
+fn client_is_principal_without_user_tokens() {
+	let authz = Authz::new(common::config_with(
+		Some(&format!("permit(principal is Jans::Client, action, resource) when {{ principal.client_id == \"{APP_ID}\" }};")),
+		None,
+	))
+	.unwrap();
+
+	let result = authz
+		.handle_raw_input(&Input::demo().to_json_without(&["id_token", "userinfo_token"]))
+		.unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+
+	// user is preferred if its tokens are given, the policy of the client is not satisfied
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert!(result
+		.user
+		.unwrap()
+		.diagnostics()
+		.reason()
+		.all(|policy| policy.to_string() != "extra"));
+}
+
+#[test]
+fn client_is_principal_if_user_tokens_are_ignored() {
+	let mut store = common::store();
+	store["policies"]["extra"] =
+		serde_json::json!({ "body": "permit(principal is Jans::Client, action, resource);" });
+	store["trustedIssuers"]["Gluu"]["idTokens"]["trusted"] = false.into();
+	store["trustedIssuers"]["Gluu"]["userinfoTokens"]["trusted"] = false.into();
+
+	let mut config = common::config_with_store(&store);
+	config.untrusted_tokens = UntrustedTokenMode::Ignore;
+	let authz = Authz::new(config).unwrap();
+
+	let input = authz::AuthzInputRaw::parse_raw(&Input::demo().to_json()).unwrap();
+	let decoded = input
+		.decode_tokens(&authz::jwt::JWTDecoder::new_without_validation())
+		.unwrap();
+	let entities = authz.get_entities(decoded.jwt).unwrap();
+	assert!(entities.user_entity_uid.is_none());
+	assert!(entities.client_entity_uid.is_some());
+
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert!(result.user.is_none());
+	assert_eq!(result.workload.unwrap().decision(), Decision::Allow);
+}
+
+#[test]
+fn user_is_created_without_access_token() {
+	let authz = common::authz();
+
+	for missing in [
+		&["access_token"][..],
+		&["access_token", "userinfo_token"],
+		&["access_token", "id_token"],
+	] {
+		let input =
+			authz::AuthzInputRaw::parse_raw(&Input::demo().to_json_without(missing)).unwrap();
+		let decoded = input
+			.decode_tokens(&authz::jwt::JWTDecoder::new_without_validation())
+			.unwrap();
+		let entities = authz.get_entities(decoded.jwt).unwrap();
+		assert!(entities.user_entity_uid.is_some(), "{missing:?}");
+		assert!(entities.client_entity_uid.is_none(), "{missing:?}");
+
+		let result = authz
+			.handle_raw_input(&Input::demo().to_json_without(missing))
+			.unwrap();
+		assert_eq!(result.decision(), Decision::Allow, "{missing:?}");
+	}
+}
+
+#[test]
+fn missing_principal_token_is_reported() {
+	let authz = Authz::new(common::config_with(
+		Some("forbid(principal, action, resource) when { false };"),
+		Some("User, Role"),
+	))
+	.unwrap();
+
+	let result =
+		authz.handle_raw_input(&Input::demo().to_json_without(&["id_token", "userinfo_token"]));
+	assert!(
+		matches!(
+			&result,
+			Err(HandleError::MissingToken { tokens, .. }) if tokens == "id_token or userinfo_token"
+		),
+		"{:?}",
+		result.err()
+	);
+
+	let result = common::authz().handle_raw_input(&Input::demo().to_json_without(&[
+		"id_token",
+		"userinfo_token",
+		"access_token",
+	]));
+	let err = result.err().unwrap();
+	assert_eq!(
+		err.to_string(),
+		r#"principal of the action Jans::Action::"Execute" can not be created without id_token or userinfo_token or access_token"#
+	);
+}
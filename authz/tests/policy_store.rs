@@ -0,0 +1,208 @@
+mod common;
+
+use authz::{Authz, AuthzNewError, GetPolicyError, PolicyStoreConfig};
+use common::Input;
+
+const LOCAL_STORE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../policy-store/local.json");
+
+fn authz(policy: PolicyStoreConfig) -> Result<Authz, AuthzNewError> {
+	let mut config = common::config();
+	config.policy = policy;
+	Authz::new(config)
+}
+
+#[test]
+fn store_is_loaded_from_file() {
+	let authz = authz(PolicyStoreConfig::File {
+		path: LOCAL_STORE_PATH.into(),
+	})
+	.unwrap();
+
+	let result = authz.handle_raw_input(&Input::demo().to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn store_is_loaded_from_json() {
+	let authz = authz(PolicyStoreConfig::Json {
+		value: common::STORE.to_owned(),
+	})
+	.unwrap();
+
+	let result = authz.handle_raw_input(&Input::demo().to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn missing_file_is_reported() {
+	let result = authz(PolicyStoreConfig::File {
+		path: "missing/policy-store.json".into(),
+	});
+	assert!(
+		matches!(
+			result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ReadFile { .. }))
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn invalid_json_is_reported() {
+	let result = authz(PolicyStoreConfig::Json {
+		value: "{".to_owned(),
+	});
+	assert!(
+		matches!(
+			result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(_)))
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn strategy_is_read_from_config_json() {
+	let config: PolicyStoreConfig =
+		serde_json::from_str(r#"{ "strategy": "file", "path": "policy-store/local.json" }"#)
+			.unwrap();
+	assert!(
+		matches!(&config, PolicyStoreConfig::File { path } if path.ends_with("local.json")),
+		"{config:?}"
+	);
+}
+
+// demo store with its policies replaced by `entry(policy_text)`
+fn store_with_policies(entry: impl Fn(&str) -> serde_json::Value) -> PolicyStoreConfig {
+	use base64::prelude::*;
+
+	let mut store = common::store();
+	let policies = store["policies"].as_object_mut().unwrap();
+	for policy in policies.values_mut() {
+		let text = BASE64_STANDARD.decode(policy.as_str().unwrap()).unwrap();
+		*policy = entry(std::str::from_utf8(&text).unwrap());
+	}
+	PolicyStoreConfig::Json {
+		value: store.to_string(),
+	}
+}
+
+fn policy_json(text: &str) -> serde_json::Value {
+	cedar_policy::Policy::parse(None, text)
+		.unwrap()
+		.to_json()
+		.unwrap()
+}
+
+#[test]
+fn policies_are_read_in_every_format() {
+	use base64::prelude::*;
+
+	let stores = [
+		store_with_policies(|text| serde_json::json!({ "body": text })),
+		store_with_policies(
+			|text| serde_json::json!({ "format": "cedar", "encoding": "base64", "body": BASE64_STANDARD.encode(text) }),
+		),
+		store_with_policies(
+			|text| serde_json::json!({ "format": "json", "body": policy_json(text) }),
+		),
+		store_with_policies(
+			|text| serde_json::json!({ "format": "json", "body": policy_json(text).to_string() }),
+		),
+		store_with_policies(|text| {
+			let json = policy_json(text).to_string();
+			serde_json::json!({ "format": "json", "encoding": "base64", "body": BASE64_STANDARD.encode(json) })
+		}),
+	];
+
+	for store in stores {
+		let authz = authz(store).unwrap();
+		let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+		assert_eq!(result.decision(), cedar_policy::Decision::Allow);
+	}
+}
+
+#[test]
+fn invalid_policy_entry_is_reported() {
+	let stores = [
+		store_with_policies(|text| serde_json::json!({ "format": "yaml", "body": text })),
+		store_with_policies(
+			|_| serde_json::json!({ "format": "cedar", "body": { "effect": "permit" } }),
+		),
+		store_with_policies(
+			|_| serde_json::json!({ "body": "permit(principal, action, resource" }),
+		),
+	];
+
+	for store in stores {
+		let result = authz(store);
+		assert!(
+			matches!(
+				&result,
+				Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+					if err.to_string().contains("b34fce229be0629e1e17baca42fbfe3621b70540598c")
+			),
+			"{:?}",
+			result.err()
+		);
+	}
+}
+
+// demo store with its schema replaced by `entry(schema_text)`
+fn store_with_schema(entry: impl Fn(&str) -> serde_json::Value) -> PolicyStoreConfig {
+	use base64::prelude::*;
+
+	let mut store = common::store();
+	let text = BASE64_STANDARD
+		.decode(store["schema"].as_str().unwrap())
+		.unwrap();
+	store["schema"] = entry(std::str::from_utf8(&text).unwrap());
+	PolicyStoreConfig::Json {
+		value: store.to_string(),
+	}
+}
+
+fn schema_json(text: &str) -> serde_json::Value {
+	let (fragment, _) = cedar_policy::SchemaFragment::from_cedarschema_str(text).unwrap();
+	fragment.to_json_value().unwrap()
+}
+
+#[test]
+fn schema_is_read_in_every_format() {
+	use base64::prelude::*;
+
+	let stores = [
+		store_with_schema(|text| serde_json::json!({ "body": text })),
+		store_with_schema(
+			|text| serde_json::json!({ "format": "json", "body": schema_json(text) }),
+		),
+		store_with_schema(|text| {
+			let json = schema_json(text).to_string();
+			serde_json::json!({ "format": "json", "encoding": "base64", "body": BASE64_STANDARD.encode(json) })
+		}),
+	];
+
+	for store in stores {
+		let authz = authz(store).unwrap();
+		let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+		assert_eq!(result.decision(), cedar_policy::Decision::Allow);
+	}
+}
+
+#[test]
+fn invalid_json_schema_is_reported() {
+	let result = authz(store_with_schema(
+		|_| serde_json::json!({ "format": "json", "body": { "Jans": { "entityTypes": [] } } }),
+	));
+	assert!(
+		matches!(
+			&result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+				if err.to_string().contains("Schema in JSON format")
+		),
+		"{:?}",
+		result.err()
+	);
+}
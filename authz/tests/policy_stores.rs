@@ -0,0 +1,107 @@
+mod common;
+
+use authz::{
+	Authz, AuthzNewError, GetPolicyError, HandleError, PolicyStoreConfig, SelectPolicyStoreError,
+	DEFAULT_POLICY_STORE_ID,
+};
+use cedar_policy::Decision;
+use common::{Input, STORE};
+
+// document with the demo store and the same store without policies
+fn document() -> String {
+	let admin = common::store();
+	let mut empty = admin.clone();
+	empty["policies"] = serde_json::json!({});
+	serde_json::json!({ "policyStores": { "admin": admin, "empty": empty } }).to_string()
+}
+
+fn authz(
+	policy_store_id: Option<&str>,
+	applications: &[(&str, &str)],
+) -> Result<Authz, AuthzNewError> {
+	let mut config = common::config();
+	config.policy = PolicyStoreConfig::Json { value: document() };
+	config.policy_store_id = policy_store_id.map(str::to_owned);
+	config.applications = applications
+		.iter()
+		.map(|(app, id)| (app.to_string(), id.to_string()))
+		.collect();
+	Authz::new(config)
+}
+
+fn input() -> authz::AuthzInputRaw {
+	authz::AuthzInputRaw::parse_raw(&Input::demo().to_json()).unwrap()
+}
+
+#[test]
+fn applications_use_own_policy_stores() {
+	let authz = authz(Some("admin"), &[("Other_App", "empty")]).unwrap();
+
+	assert_eq!(authz.handle(input()).unwrap().decision(), Decision::Allow);
+	assert_eq!(
+		authz.handle_app("Other_App", input()).unwrap().decision(),
+		Decision::Deny
+	);
+
+	let result = authz.handle_app("Missing_App", input());
+	assert!(
+		matches!(&result, Err(HandleError::UnknownApplication(app)) if app == "Missing_App"),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn policy_store_should_be_selected() {
+	let result = authz(None, &[]);
+	assert!(
+		matches!(
+			result,
+			Err(AuthzNewError::SelectPolicyStore {
+				source: SelectPolicyStoreError::NotSelected(2),
+				..
+			})
+		),
+		"{:?}",
+		result.err()
+	);
+
+	let result = authz(Some("admin"), &[("Other_App", "missing")]);
+	assert!(
+		matches!(
+			&result,
+			Err(AuthzNewError::SelectPolicyStore {
+				app: Some(app),
+				source: SelectPolicyStoreError::NotFound(id),
+			}) if app == "Other_App" && id == "missing"
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn single_store_has_default_id() {
+	let mut config = common::config();
+	config.policy_store_id = Some(DEFAULT_POLICY_STORE_ID.to_owned());
+	let authz = Authz::new(config).unwrap();
+	assert_eq!(authz.handle(input()).unwrap().decision(), Decision::Allow);
+}
+
+#[test]
+fn repeated_store_id_is_reported() {
+	let mut config = common::config();
+	config.policy = PolicyStoreConfig::Json {
+		value: format!(r#"{{ "policyStores": {{ "admin": {STORE}, "admin": {STORE} }} }}"#),
+	};
+	let result = Authz::new(config);
+	assert!(
+		matches!(
+			&result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+				if err.to_string().contains(r#"policy store "admin" is defined twice"#)
+		),
+		"{:?}",
+		result.err()
+	);
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::Input;
+
+const GOOGLE: &str = "https://accounts.google.com";
+
+#[test]
+fn user_id_is_sub_without_principal_identifier() {
+	let input = Input::demo();
+	let entities = input.entities(&common::authz()).unwrap();
+
+	let uid = common::user_uid(&entities);
+	assert_eq!(
+		uid.id().escaped(),
+		input.userinfo_token["sub"].as_str().unwrap()
+	);
+}
+
+#[test]
+fn user_id_is_taken_from_principal_identifier_claim() {
+	// Google issuer is configured with `principalIdentifier: email`
+	let input = Input::demo().with_issuer(GOOGLE);
+	let entities = input.entities(&common::authz()).unwrap();
+
+	let uid = common::user_uid(&entities);
+	assert_eq!(uid.id().escaped(), "admin@admin-ui-test.gluu.org");
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use authz::{Authz, DecisionRule, HandleError, PrincipalMode, UntrustedTokenMode};
+use cedar_policy::Decision;
+use common::{Input, APP_ID};
+
+// demo config with additional policy, `principals` of the action replace the declared ones
+fn authz(mode: PrincipalMode, policy: Option<&str>, principals: Option<&str>) -> Authz {
+	let mut config = common::config_with(policy, principals);
+	config.principal_mode = mode;
+	Authz::new(config).unwrap()
+}
+
+#[test]
+fn workload_is_principal_in_workload_mode() {
+	// the demo policy allows only the user
+	let result = authz(PrincipalMode::Workload, None, None)
+		.handle_raw_input(&Input::demo().to_json())
+		.unwrap();
+	assert_eq!(result.decision(), Decision::Deny);
+	assert!(result.user.is_none());
+	assert!(result.workload.is_some());
+
+	let authz = authz(
+		PrincipalMode::Workload,
+		Some("permit(principal is Jans::Client, action, resource);"),
+		None,
+	);
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+
+	let err = authz
+		.handle_raw_input(&Input::demo().to_json_without(&["access_token"]))
+		.err()
+		.unwrap();
+	assert!(
+		matches!(&err, HandleError::MissingToken { tokens, .. } if tokens == "access_token"),
+		"{err:?}"
+	);
+}
+
+#[test]
+fn client_is_keyed_by_client_id() {
+	let mut input = Input::demo();
+	input.access_token["aud"] = "https://api.test".into();
+
+	let entities = input.entities(&common::authz()).unwrap();
+	let client = common::entity_json(&entities, "Jans::Client");
+	assert_eq!(client["uid"]["id"], APP_ID);
+}
+
+#[test]
+fn ignored_access_token_is_not_workload() {
+	let mut store = common::store();
+	store["trustedIssuers"]["Gluu"]["accessTokens"]["trusted"] = false.into();
+	let authz = |mode| {
+		let mut config = common::config_with_store(&store);
+		config.untrusted_tokens = UntrustedTokenMode::Ignore;
+		config.principal_mode = mode;
+		Authz::new(config).unwrap()
+	};
+
+	for mode in [
+		PrincipalMode::Workload,
+		PrincipalMode::Combined(DecisionRule::Or),
+	] {
+		let result = authz(mode).handle_raw_input(&Input::demo().to_json());
+		assert!(
+			matches!(&result, Err(HandleError::MissingToken { tokens, .. }) if tokens == "access_token"),
+			"{mode:?}: {:?}",
+			result.err()
+		);
+	}
+}
+
+#[test]
+fn application_is_workload_if_action_accepts_it() {
+	let authz = authz(
+		PrincipalMode::Workload,
+		Some("permit(principal is Jans::Application, action, resource) when { principal.name == \"Demo_App\" };"),
+		Some("User, Application"),
+	);
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+}
+
+#[test]
+fn user_mode_requires_user_tokens() {
+	let result = authz(PrincipalMode::User, None, None)
+		.handle_raw_input(&Input::demo().to_json_without(&["id_token", "userinfo_token"]));
+	assert!(
+		matches!(
+			&result,
+			Err(HandleError::MissingToken { tokens, .. }) if tokens == "id_token or userinfo_token"
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn combined_mode_returns_both_responses() {
+	// the demo policy allows the user, but not the workload
+	for (rule, expected) in [
+		(DecisionRule::And, Decision::Deny),
+		(DecisionRule::Or, Decision::Allow),
+	] {
+		let result = authz(PrincipalMode::Combined(rule), None, None)
+			.handle_raw_input(&Input::demo().to_json())
+			.unwrap();
+		assert_eq!(result.decision(), expected, "{rule:?}");
+		assert_eq!(result.user.unwrap().decision(), Decision::Allow);
+		assert_eq!(result.workload.unwrap().decision(), Decision::Deny);
+	}
+
+	let authz = authz(
+		PrincipalMode::Combined(DecisionRule::And),
+		Some("permit(principal is Jans::Client, action, resource);"),
+		None,
+	);
+	let result = authz.handle_raw_input(&Input::demo().to_json()).unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+
+	// both principals are required
+	let result = authz.handle_raw_input(&Input::demo().to_json_without(&["access_token"]));
+	assert!(
+		matches!(&result, Err(HandleError::MissingToken { .. })),
+		"{:?}",
+		result.err()
+	);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use authz::{Authz, AuthzNewError, GetPolicyError, PolicyStoreConfig};
+use cedar_policy::Decision;
+use common::{Input, STORE};
+
+// the same store without policies, so every request is denied
+fn store_without_policies() -> String {
+	let mut store = common::store();
+	store["policies"] = serde_json::json!({});
+	store.to_string()
+}
+
+fn store_path(test: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("authz-reload-{}-{test}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("policy-store.json");
+	std::fs::write(&path, STORE).unwrap();
+	path
+}
+
+fn authz(path: &Path) -> Authz {
+	let mut config = common::config();
+	config.policy = PolicyStoreConfig::File {
+		path: path.to_owned(),
+	};
+	Authz::new(config).unwrap()
+}
+
+fn decision(authz: &Authz) -> Decision {
+	authz
+		.handle_raw_input(&Input::demo().to_json())
+		.unwrap()
+		.decision()
+}
+
+#[test]
+fn changed_file_is_reloaded() {
+	let path = store_path("changed");
+	let authz = authz(&path);
+	assert_eq!(decision(&authz), Decision::Allow);
+
+	assert!(!authz.reload_if_changed().unwrap());
+
+	std::fs::write(&path, store_without_policies()).unwrap();
+	assert!(authz.reload_if_changed().unwrap());
+	assert_eq!(decision(&authz), Decision::Deny);
+	assert!(!authz.reload_if_changed().unwrap());
+
+	std::fs::write(&path, STORE).unwrap();
+	authz.reload().unwrap();
+	assert_eq!(decision(&authz), Decision::Allow);
+}
+
+#[test]
+fn invalid_store_is_rejected_on_reload() {
+	let path = store_path("invalid");
+	let authz = authz(&path);
+
+	std::fs::write(&path, "{").unwrap();
+	let result = authz.reload();
+	assert!(
+		matches!(
+			result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(_)))
+		),
+		"{result:?}"
+	);
+	assert!(authz.reload_if_changed().is_err());
+
+	// previous store is kept
+	assert_eq!(decision(&authz), Decision::Allow);
+}
+
+#[test]
+fn static_store_is_not_reloaded() {
+	let authz = common::authz();
+	assert!(!authz.reload_if_changed().unwrap());
+	authz.reload().unwrap();
+	assert_eq!(decision(&authz), Decision::Allow);
+}
+
+#[test]
+fn watcher_reloads_changed_file() {
+	let path = store_path("watcher");
+	let authz = Arc::new(authz(&path));
+	let _watcher = authz.watch(Duration::from_millis(10));
+
+	std::fs::write(&path, store_without_policies()).unwrap();
+
+	let started = Instant::now();
+	while decision(&authz) != Decision::Deny {
+		assert!(
+			started.elapsed() < Duration::from_secs(5),
+			"policy store is not reloaded"
+		);
+		std::thread::sleep(Duration::from_millis(10));
+	}
+}
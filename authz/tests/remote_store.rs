@@ -0,0 +1,283 @@
+mod common;
+
+use std::{
+	io::{BufRead, BufReader, Write},
+	net::TcpListener,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use authz::{
+	jwt::{HttpClient, HttpError, HttpResponse},
+	Authz, AuthzNewError, GetPolicyError, PolicyStoreConfig, RemoteStoreError, RemoteTransport,
+};
+use common::STORE;
+
+const URL: &str = "https://store.test/policy-store.json";
+
+// responds with queued responses and records sent ETags
+#[derive(Default)]
+struct StubTransport {
+	responses: Mutex<Vec<Result<HttpResponse, HttpError>>>,
+	etags: Mutex<Vec<Option<String>>>,
+}
+
+impl StubTransport {
+	fn new(responses: Vec<Result<HttpResponse, HttpError>>) -> Arc<Self> {
+		Arc::new(Self {
+			responses: Mutex::new(responses.into_iter().rev().collect()),
+			..Default::default()
+		})
+	}
+}
+
+impl HttpClient for StubTransport {
+	fn get_if_none_match(&self, _url: &str, etag: Option<&str>) -> Result<HttpResponse, HttpError> {
+		self.etags.lock().unwrap().push(etag.map(str::to_owned));
+		self.responses.lock().unwrap().pop().unwrap()
+	}
+}
+
+fn modified(etag: &str) -> Result<HttpResponse, HttpError> {
+	Ok(HttpResponse::Modified {
+		body: STORE.to_owned(),
+		etag: Some(etag.to_owned()),
+	})
+}
+
+// the document is parsed, but its policy is rejected by the validation
+fn invalid(etag: &str) -> Result<HttpResponse, HttpError> {
+	let mut store = common::store();
+	store["policies"]["extra"] = serde_json::json!({
+		"body": "permit(principal is Jans::User, action, resource) when { principal.emial == \"admin\" };"
+	});
+	Ok(HttpResponse::Modified {
+		body: store.to_string(),
+		etag: Some(etag.to_owned()),
+	})
+}
+
+fn unavailable() -> Result<HttpResponse, HttpError> {
+	Err(HttpError {
+		url: URL.to_owned(),
+		message: "connection refused".to_owned(),
+	})
+}
+
+fn cache_path(test: &str) -> PathBuf {
+	let dir =
+		std::env::temp_dir().join(format!("authz-remote-store-{}-{test}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	dir.join("policy-store.json")
+}
+
+fn load(cache_path: &Path, transport: Arc<dyn HttpClient>) -> Result<Authz, AuthzNewError> {
+	let mut config = common::config();
+	config.policy = PolicyStoreConfig::Remote {
+		url: URL.to_owned(),
+		cache_path: cache_path.to_owned(),
+		transport: RemoteTransport(transport),
+	};
+	Authz::new(config)
+}
+
+#[test]
+fn store_is_downloaded_and_revalidated_with_etag() {
+	let cache = cache_path("revalidated");
+	let transport = StubTransport::new(vec![modified("\"v1\""), Ok(HttpResponse::NotModified)]);
+
+	load(&cache, transport.clone()).unwrap();
+	assert_eq!(std::fs::read_to_string(&cache).unwrap(), STORE);
+
+	// cached copy is used when the store is not modified
+	let authz = load(&cache, transport.clone()).unwrap();
+	let result = authz.handle_raw_input(&common::Input::demo().to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+
+	assert_eq!(
+		*transport.etags.lock().unwrap(),
+		vec![None, Some("\"v1\"".to_owned())]
+	);
+}
+
+#[test]
+fn cached_copy_is_used_if_fetch_fails() {
+	let cache = cache_path("fallback");
+	let transport = StubTransport::new(vec![
+		modified("\"v1\""),
+		unavailable(),
+		Ok(HttpResponse::Modified {
+			body: "not a policy store".to_owned(),
+			etag: Some("\"v2\"".to_owned()),
+		}),
+	]);
+
+	load(&cache, transport.clone()).unwrap();
+	load(&cache, transport.clone()).unwrap();
+	load(&cache, transport.clone()).unwrap();
+
+	// invalid document does not replace the cached copy
+	assert_eq!(std::fs::read_to_string(&cache).unwrap(), STORE);
+}
+
+#[test]
+fn fetch_error_without_cache_is_reported() {
+	let cache = cache_path("no-cache");
+	let result = load(&cache, StubTransport::new(vec![unavailable()]));
+	assert!(
+		matches!(
+			result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::Remote(
+				RemoteStoreError::Transport(_)
+			)))
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+// serves two requests: the store with ETag, then `304` if the ETag is sent back
+fn serve_store() -> (String, std::thread::JoinHandle<Vec<Option<String>>>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!(
+		"http://{}/policy-store.json",
+		listener.local_addr().unwrap()
+	);
+
+	let server = std::thread::spawn(move || {
+		let mut if_none_match = Vec::new();
+		for stream in listener.incoming().take(2) {
+			let mut stream = stream.unwrap();
+			let mut etag = None;
+			for line in BufReader::new(&stream).lines() {
+				let line = line.unwrap();
+				if line.is_empty() {
+					break;
+				}
+				if let Some((name, value)) = line.split_once(": ") {
+					if name.eq_ignore_ascii_case("if-none-match") {
+						etag = Some(value.to_owned());
+					}
+				}
+			}
+
+			let response = if etag.as_deref() == Some("\"v1\"") {
+				"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_owned()
+			} else {
+				format!(
+					"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{STORE}",
+					STORE.len()
+				)
+			};
+			stream.write_all(response.as_bytes()).unwrap();
+			if_none_match.push(etag);
+		}
+		if_none_match
+	});
+	(url, server)
+}
+
+#[test]
+fn ureq_transport_sends_if_none_match() {
+	let (url, server) = serve_store();
+	let cache = cache_path("ureq");
+
+	for _ in 0..2 {
+		let mut config = common::config();
+		config.policy = PolicyStoreConfig::Remote {
+			url: url.clone(),
+			cache_path: cache.clone(),
+			transport: RemoteTransport::default(),
+		};
+		Authz::new(config).unwrap();
+	}
+
+	assert_eq!(
+		server.join().unwrap(),
+		vec![None, Some("\"v1\"".to_owned())]
+	);
+	assert_eq!(
+		std::fs::read_to_string(format!("{}.etag", cache.display())).unwrap(),
+		"\"v1\""
+	);
+}
+
+#[test]
+fn store_is_reloaded_when_etag_changes() {
+	let cache = cache_path("reload");
+	let transport = StubTransport::new(vec![
+		modified("\"v1\""),
+		Ok(HttpResponse::NotModified),
+		modified("\"v2\""),
+		Ok(HttpResponse::NotModified),
+	]);
+
+	let authz = load(&cache, transport.clone()).unwrap();
+	assert!(!authz.reload_if_changed().unwrap());
+	assert!(authz.reload_if_changed().unwrap());
+	assert!(!authz.reload_if_changed().unwrap());
+
+	assert_eq!(
+		*transport.etags.lock().unwrap(),
+		vec![
+			None,
+			Some("\"v1\"".to_owned()),
+			Some("\"v1\"".to_owned()),
+			Some("\"v2\"".to_owned())
+		]
+	);
+	assert_eq!(
+		std::fs::read_to_string(format!("{}.etag", cache.display())).unwrap(),
+		"\"v2\""
+	);
+}
+
+#[test]
+fn invalid_store_does_not_replace_cache() {
+	let cache = cache_path("invalid");
+	let etag_path = format!("{}.etag", cache.display());
+	let transport = StubTransport::new(vec![
+		invalid("\"v2\""),
+		modified("\"v1\""),
+		invalid("\"v2\""),
+	]);
+
+	let result = load(&cache, transport.clone());
+	assert!(
+		matches!(result, Err(AuthzNewError::Validation(_))),
+		"{:?}",
+		result.err()
+	);
+	assert!(!cache.exists());
+
+	let authz = load(&cache, transport.clone()).unwrap();
+	let result = authz.reload_if_changed();
+	assert!(
+		matches!(result, Err(AuthzNewError::Validation(_))),
+		"{:?}",
+		result.err()
+	);
+	assert_eq!(std::fs::read_to_string(&cache).unwrap(), STORE);
+	assert_eq!(std::fs::read_to_string(&etag_path).unwrap(), "\"v1\"");
+}
+
+#[test]
+fn cached_copy_is_used_if_downloaded_store_is_rejected() {
+	let cache = cache_path("rejected");
+	let transport = StubTransport::new(vec![modified("\"v1\""), invalid("\"v2\"")]);
+
+	load(&cache, transport.clone()).unwrap();
+	let authz = load(&cache, transport.clone()).unwrap();
+	let result = authz.handle_raw_input(&common::Input::demo().to_json());
+	assert_eq!(result.unwrap().decision(), cedar_policy::Decision::Allow);
+
+	assert_eq!(
+		*transport.etags.lock().unwrap(),
+		vec![None, Some("\"v1\"".to_owned())]
+	);
+	assert_eq!(std::fs::read_to_string(&cache).unwrap(), STORE);
+	assert_eq!(
+		std::fs::read_to_string(format!("{}.etag", cache.display())).unwrap(),
+		"\"v1\""
+	);
+}
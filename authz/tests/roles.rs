@@ -0,0 +1,54 @@
+mod common;
+
+use common::Input;
+use serde_json::json;
+
+fn user_roles(input: &Input) -> (serde_json::Value, serde_json::Value) {
+	let entities = input.entities(&common::authz()).unwrap();
+	let user = common::entity_json(&entities, "Jans::User");
+	(user["attrs"]["role"].clone(), user["parents"].clone())
+}
+
+fn role_uids(roles: &[&str]) -> serde_json::Value {
+	roles
+		.iter()
+		.map(|role| json!({ "type": "Jans::Role", "id": role }))
+		.collect()
+}
+
+#[test]
+fn roles_are_read_from_role_mapping_claim() {
+	// Gluu issuer is configured with `roleMapping: jansAdminUIRole`
+	let (role, parents) = user_roles(&Input::demo());
+	assert_eq!(role, json!(["api-admin"]));
+	assert_eq!(parents, role_uids(&["api-admin"]));
+}
+
+#[test]
+fn roles_are_joined_from_all_tokens() {
+	// Google issuer is configured with `roleMapping: role`
+	let mut input = Input::demo().with_issuer("https://accounts.google.com");
+	input.userinfo_token["role"] = json!(["viewer", "editor"]);
+	input.id_token["role"] = json!("editor");
+	input.access_token["role"] = json!("admin");
+
+	// sets are serialized in sorted order
+	let (role, parents) = user_roles(&input);
+	assert_eq!(role, json!(["admin", "editor", "viewer"]));
+
+	let mut parents: Vec<_> = parents.as_array().unwrap().clone();
+	parents.sort_by_key(|p| p["id"].to_string());
+	assert_eq!(
+		serde_json::Value::Array(parents),
+		role_uids(&["admin", "editor", "viewer"])
+	);
+}
+
+#[test]
+fn user_without_role_claim_has_no_roles() {
+	let input = Input::demo().with_issuer("https://accounts.google.com");
+
+	let (role, parents) = user_roles(&input);
+	assert_eq!(role, json!([]));
+	assert_eq!(parents, json!([]));
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use authz::{Authz, HandleError};
+use cedar_policy::Decision;
+use common::{Input, SCHEMA};
+use serde_json::json;
+
+// demo store where `attrs` are added to the declaration of the entity type `entity`
+fn authz(entity: &str, attrs: &str, policy: &str) -> Authz {
+	let declaration = format!("entity {entity} ");
+	let (head, tail) = SCHEMA.split_once(&declaration).unwrap();
+	let (shape, tail) = tail.split_once("};").unwrap();
+	let schema = format!("{head}{declaration}{shape}{attrs}\n    }};{tail}");
+
+	let mut store = common::store();
+	store["schema"] = json!({ "body": schema });
+	store["policies"] = json!({ "schema_entities": { "body": policy } });
+
+	Authz::new(common::config_with_store(&store)).unwrap()
+}
+
+fn entities_error(authz: &Authz, input: &Input) -> String {
+	match input.entities(authz) {
+		Err(err @ HandleError::AuthzInputEntities(_)) => err.to_string(),
+		result => panic!("{:?}", result.map(|_| ())),
+	}
+}
+
+#[test]
+fn claims_declared_in_schema_become_attributes() {
+	let authz = authz(
+		"User in [Role]",
+		"nickname: String, amr: Set<String>,",
+		"permit(principal is Jans::User, action, resource) when { principal.nickname == \"Admin\" && principal.amr.contains(\"10\") };",
+	);
+
+	let mut input = Input::demo();
+	assert_eq!(
+		authz.handle_raw_input(&input.to_json()).unwrap().decision(),
+		Decision::Allow
+	);
+
+	// claims of userinfo_token have priority over id_token
+	input.userinfo_token["nickname"] = "Guest".into();
+	assert_eq!(
+		authz.handle_raw_input(&input.to_json()).unwrap().decision(),
+		Decision::Deny
+	);
+}
+
+#[test]
+fn nested_and_optional_claims_are_mapped() {
+	let authz = authz(
+		"id_token ",
+		"status: { status_list: { idx: Long, uri: String } }, country?: String,",
+		"permit(principal, action, resource);",
+	);
+
+	let mut input = Input::demo();
+	let entities = input.entities(&authz).unwrap();
+	let id_token = common::entity_json(&entities, "Jans::id_token");
+	assert_eq!(id_token["attrs"]["status"]["status_list"]["idx"], 2004);
+	assert_eq!(id_token["attrs"]["country"], "HU");
+
+	input.id_token["country"].take();
+	let entities = input.entities(&authz).unwrap();
+	let id_token = common::entity_json(&entities, "Jans::id_token");
+	assert!(id_token["attrs"].get("country").is_none(), "{id_token}");
+}
+
+#[test]
+fn invalid_claims_are_reported() {
+	let authz = common::authz();
+
+	let mut input = Input::demo();
+	input.id_token["birthdate"].take();
+	let err = entities_error(&authz, &input);
+	assert!(
+		err.contains("required claim `birthdate` is missing"),
+		"{err}"
+	);
+
+	let mut input = Input::demo();
+	input.access_token["exp"] = "tomorrow".into();
+	let err = entities_error(&authz, &input);
+	assert!(
+		err.contains(r#"claim `exp` is expected to be an integer, but it is "tomorrow""#),
+		"{err}"
+	);
+}
+
+#[test]
+fn only_email_address_and_url_types_are_parsed_from_strings() {
+	// record with the fields of `email_address` isn't the email
+	let anonymous = authz(
+		"id_token ",
+		"contact?: { id: String, domain: String },",
+		"permit(principal, action, resource);",
+	);
+	let mut input = Input::demo();
+	input.id_token["contact"] = "admin@admin-ui-test.gluu.org".into();
+	let err = entities_error(&anonymous, &input);
+	assert!(
+		err.contains("claim `contact` is expected to be an object"),
+		"{err}"
+	);
+
+	let named = authz(
+		"id_token ",
+		"contact?: email_address, site?: Url,",
+		"permit(principal, action, resource);",
+	);
+	input.id_token["site"] = "https://admin-ui-test.gluu.org/admin".into();
+	let entities = input.entities(&named).unwrap();
+	let attrs = common::entity_json(&entities, "Jans::id_token")["attrs"].clone();
+	assert_eq!(
+		attrs["contact"],
+		json!({ "id": "admin", "domain": "admin-ui-test.gluu.org" })
+	);
+	assert_eq!(attrs["site"]["path"], "/admin");
+}
+
+#[test]
+fn access_token_entity_is_keyed_by_jti() {
+	let authz = common::authz();
+
+	let mut input = Input::demo();
+	let entities = input.entities(&authz).unwrap();
+	let access_token = common::entity_json(&entities, "Jans::Access_token");
+	assert_eq!(
+		access_token["uid"],
+		json!({ "type": "Jans::Access_token", "id": "BvI3PibJS6KelAal2ztooA" })
+	);
+	let attrs = &access_token["attrs"];
+	assert_eq!(attrs["token_type"], "Bearer");
+	assert_eq!(attrs["auth_time"], 1725018931);
+	assert_eq!(attrs["x5t#S256"], "");
+	assert_eq!(attrs["status"]["status_list"]["idx"], 2003);
+
+	// optional claims of the access token can be missing
+	for claim in [
+		"sub",
+		"acr",
+		"auth_time",
+		"token_type",
+		"x5t#S256",
+		"status",
+	] {
+		input.access_token[claim].take();
+	}
+	input.access_token["jti"] = "other".into();
+	let entities = input.entities(&authz).unwrap();
+	let access_token = common::entity_json(&entities, "Jans::Access_token");
+	assert_eq!(access_token["uid"]["id"], "other");
+	assert!(
+		access_token["attrs"].get("status").is_none(),
+		"{access_token}"
+	);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use authz::{Authz, AuthzNewError, EntityConflictMode, GetPolicyError, HandleError};
+use cedar_policy::{Decision, EntityUid};
+use common::{Input, APP_ID};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+fn application(name: &str) -> Value {
+	json!({
+		"uid": { "type": "Jans::Application", "id": APP_ID },
+		"attrs": {
+			"name": name,
+			"client": { "__entity": { "type": "Jans::Client", "id": APP_ID } }
+		},
+		"parents": []
+	})
+}
+
+fn authz(entities: Value, entity_conflicts: EntityConflictMode) -> Result<Authz, AuthzNewError> {
+	let mut store = common::store();
+	store["policies"] = json!({
+		"store_app": {
+			"body": "permit(principal, action, resource) when { resource.name == \"Store App\" };"
+		}
+	});
+	store["entities"] = entities;
+
+	let mut config = common::config_with_store(&store);
+	config.entity_conflicts = entity_conflicts;
+	Authz::new(config)
+}
+
+#[test]
+fn store_entities_are_added_to_request() {
+	let authz = authz(
+		json!([{ "uid": { "type": "Jans::Role", "id": "Auditor" }, "attrs": {}, "parents": [] }]),
+		EntityConflictMode::default(),
+	)
+	.unwrap();
+
+	let entities = Input::demo().entities(&authz).unwrap();
+	assert!(entities
+		.get(&EntityUid::from_str(r#"Jans::Role::"Auditor""#).unwrap())
+		.is_some());
+	assert!(entities
+		.get(&EntityUid::from_str(r#"Jans::Role::"api-admin""#).unwrap())
+		.is_some());
+}
+
+#[test]
+fn entity_conflict_is_resolved_by_config() {
+	let input = Input::demo().to_json();
+
+	let authz_store = authz(
+		json!([application("Store App")]),
+		EntityConflictMode::Reject,
+	)
+	.unwrap();
+	let result = authz_store.handle_raw_input(&input);
+	assert!(
+		matches!(&result, Err(HandleError::EntityConflict(uid)) if uid.contains(APP_ID)),
+		"{:?}",
+		result.err()
+	);
+
+	let authz_store = authz(
+		json!([application("Store App")]),
+		EntityConflictMode::PreferStore,
+	)
+	.unwrap();
+	let result = authz_store.handle_raw_input(&input).unwrap();
+	assert_eq!(result.decision(), Decision::Allow);
+
+	let authz_store = authz(
+		json!([application("Store App")]),
+		EntityConflictMode::PreferToken,
+	)
+	.unwrap();
+	let result = authz_store.handle_raw_input(&input).unwrap();
+	assert_eq!(result.decision(), Decision::Deny);
+}
+
+#[test]
+fn entities_are_validated_against_schema() {
+	let mut entity = application("Store App");
+	entity["attrs"]["owner"] = json!("admin");
+
+	let result = authz(json!([entity]), EntityConflictMode::default());
+	assert!(
+		matches!(
+			&result,
+			Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+				if err.to_string().contains("unable to parse entities")
+		),
+		"{:?}",
+		result.err()
+	);
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use authz::{Authz, AuthzNewError, GetPolicyError, PolicyStoreConfig};
+use cedar_policy::Decision;
+use common::{Input, APP_ID, STORE};
+use serde_json::{json, Value};
+
+// demo store where the access is granted only by template links
+fn authz(template_links: Value) -> Result<Authz, AuthzNewError> {
+	let mut store = common::store();
+	store["policies"] = json!({});
+	store["templates"] = json!({
+		"execute_app": {
+			"body": "permit(principal is Jans::User, action == Jans::Action::\"Execute\", resource == ?resource);"
+		}
+	});
+	store["templateLinks"] = template_links;
+
+	Authz::new(common::config_with_store(&store))
+}
+
+fn decision(authz: &Authz) -> Decision {
+	authz
+		.handle_raw_input(&Input::demo().to_json())
+		.unwrap()
+		.decision()
+}
+
+#[test]
+fn template_links_grant_access() {
+	let granted = authz(json!([
+		{
+			"templateId": "execute_app",
+			"newId": "tenant_a",
+			"values": { "?resource": { "type": "Jans::Application", "id": APP_ID } }
+		}
+	]))
+	.unwrap();
+	assert_eq!(decision(&granted), Decision::Allow);
+
+	let other_app = authz(json!([
+		{
+			"templateId": "execute_app",
+			"newId": "tenant_b",
+			"values": { "?resource": "Jans::Application::\"other\"" }
+		}
+	]))
+	.unwrap();
+	assert_eq!(decision(&other_app), Decision::Deny);
+}
+
+#[test]
+fn invalid_template_links_are_rejected() {
+	let links = [
+		json!({ "templateId": "missing", "newId": "tenant_a", "values": {} }),
+		json!({ "templateId": "execute_app", "newId": "tenant_a", "values": {} }),
+		json!({
+			"templateId": "execute_app",
+			"newId": "tenant_a",
+			"values": { "?owner": "Jans::User::\"alice\"" }
+		}),
+		json!({
+			"templateId": "execute_app",
+			"newId": "tenant_a",
+			"values": { "?resource": "not an entity uid" }
+		}),
+	];
+
+	for link in links {
+		let result = authz(json!([link]));
+		assert!(
+			matches!(
+				&result,
+				Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err)))
+					if err.to_string().contains("tenant_a")
+			),
+			"{link}: {:?}",
+			result.err()
+		);
+	}
+}
+
+fn parse_error(result: Result<Authz, AuthzNewError>) -> String {
+	match result {
+		Err(AuthzNewError::PolicyStore(GetPolicyError::ParseJson(err))) => err.to_string(),
+		result => panic!("{:?}", result.err()),
+	}
+}
+
+#[test]
+fn policy_id_collisions_are_reported() {
+	let link = |new_id: &str| {
+		json!({
+			"templateId": "execute_app",
+			"newId": new_id,
+			"values": { "?resource": { "type": "Jans::Application", "id": APP_ID } }
+		})
+	};
+
+	let err = parse_error(authz(json!([link("execute_app")])));
+	assert!(
+		err.contains(r#"policy id "execute_app" of template link "execute_app" of template "execute_app" is already used by template "execute_app""#),
+		"{err}"
+	);
+
+	let err = parse_error(authz(json!([link("tenant_a"), link("tenant_a")])));
+	assert!(
+		err.contains(r#"is already used by template link "tenant_a" of template "execute_app""#),
+		"{err}"
+	);
+}
+
+#[test]
+fn static_policy_and_template_with_same_id_are_reported() {
+	let mut store = common::store();
+	store["templates"] = json!({
+		"b34fce229be0629e1e17baca42fbfe3621b70540598c": {
+			"body": "permit(principal, action, resource == ?resource);"
+		}
+	});
+
+	let err = parse_error(Authz::new(common::config_with_store(&store)));
+	assert!(
+		err.contains(r#"of template "b34fce229be0629e1e17baca42fbfe3621b70540598c" is already used by policy "b34fce229be0629e1e17baca42fbfe3621b70540598c""#),
+		"{err}"
+	);
+}
+
+#[test]
+fn repeated_policy_key_is_reported() {
+	let mut config = common::config();
+	config.policy = PolicyStoreConfig::Json {
+		value: STORE.replacen(
+			r#""policies": {"#,
+			r#""policies": { "b34fce229be0629e1e17baca42fbfe3621b70540598c": { "body": "forbid(principal, action, resource);" },"#,
+			1,
+		),
+	};
+	let err = parse_error(Authz::new(config));
+	assert!(
+		err.contains(r#"policy "b34fce229be0629e1e17baca42fbfe3621b70540598c" is defined twice"#),
+		"{err}"
+	);
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use authz::{Authz, HandleError, TokenKind, TrustedIssuerError, UntrustedTokenMode};
+use common::Input;
+use serde_json::{json, Value};
+
+const OTHER_ISSUER: &str = "https://other.test";
+
+// demo store with issuer whose tokens are not trusted
+fn untrusted_authz(mode: UntrustedTokenMode) -> Authz {
+	let mut store = common::store();
+	store["trustedIssuers"]["Other"] = json!({
+		"openidConfigurationEndpoint": "https://other.test/.well-known/openid-configuration",
+		"accessTokens": { "trusted": false },
+		"idTokens": { "trusted": false, "principalIdentifier": "email" },
+		"userinfoTokens": { "trusted": false, "roleMapping": "jansAdminUIRole" },
+	});
+
+	let mut config = common::config_with_store(&store);
+	config.untrusted_tokens = mode;
+	Authz::new(config).unwrap()
+}
+
+// demo input where `field` token is issued by the untrusted issuer
+// and its claims try to change the user and its roles
+fn input_with_untrusted(field: &str) -> Input {
+	let mut input = Input::demo();
+	let token = match field {
+		"id_token" => &mut input.id_token,
+		"userinfo_token" => &mut input.userinfo_token,
+		_ => &mut input.access_token,
+	};
+	token["iss"] = OTHER_ISSUER.into();
+	token["sub"] = "intruder".into();
+	token["email"] = "intruder@other.test".into();
+	token["jansAdminUIRole"] = json!(["intruder"]);
+	input
+}
+
+// entities json sorted by uid to compare lists
+fn entities_json(input: &str, authz: &Authz) -> Vec<Value> {
+	let input = authz::AuthzInputRaw::parse_raw(input).unwrap();
+	let decoded = input
+		.decode_tokens(&jwt::JWTDecoder::new_without_validation())
+		.unwrap();
+	let entities = authz.get_entities(decoded.jwt).unwrap().entities;
+	let mut list: Vec<_> = entities.iter().collect();
+	list.sort_by_key(|entity| entity.uid().to_string());
+	list.into_iter()
+		.map(|entity| entity.to_json_value().unwrap())
+		.collect()
+}
+
+const TOKEN_FIELDS: [(TokenKind, &str); 3] = [
+	(TokenKind::IdToken, "id_token"),
+	(TokenKind::UserinfoToken, "userinfo_token"),
+	(TokenKind::AccessToken, "access_token"),
+];
+
+#[test]
+fn token_of_trusted_issuer_is_accepted() {
+	let result = common::authz().handle_raw_input(&Input::demo().to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn token_of_untrusted_issuer_is_rejected() {
+	let cases = [
+		(TokenKind::IdToken, "id_token"),
+		(TokenKind::UserinfoToken, "userinfo_token"),
+		(TokenKind::AccessToken, "access_token"),
+	];
+
+	for (expected, field) in cases {
+		let mut input = Input::demo();
+		let token = match field {
+			"id_token" => &mut input.id_token,
+			"userinfo_token" => &mut input.userinfo_token,
+			_ => &mut input.access_token,
+		};
+		token["iss"] = "https://other.test".into();
+
+		let result = common::authz().handle_raw_input(&input.to_json());
+		assert!(
+			matches!(
+				&result,
+				Err(HandleError::TrustedIssuer(TrustedIssuerError::UntrustedIssuer { kind, iss }))
+					if *kind == expected && iss == "https://other.test"
+			),
+			"{field}: {:?}",
+			result.err()
+		);
+	}
+}
+
+#[test]
+fn issuer_is_matched_without_trailing_slash() {
+	let mut input = Input::demo();
+	input.access_token["iss"] = "https://admin-ui-test.gluu.org/".into();
+
+	let mut config = common::config();
+	config.consistency.same_issuer = false;
+	let result = authz::Authz::new(config)
+		.unwrap()
+		.handle_raw_input(&input.to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+}
+
+#[test]
+fn issuer_entities_are_added() {
+	let entities = Input::demo().entities(&common::authz()).unwrap();
+
+	let issuer = common::entity_json(&entities, "Jans::TrustedIssuer");
+	assert_eq!(issuer["uid"]["id"], "https://admin-ui-test.gluu.org");
+	assert_eq!(issuer["attrs"]["name"], "Gluu Admin UI");
+	assert_eq!(
+		issuer["attrs"]["issuer_entity_id"]["host"],
+		"admin-ui-test.gluu.org"
+	);
+}
+
+#[test]
+fn issuer_attributes_are_set_by_schema() {
+	let schema = common::SCHEMA.replacen(
+		"        issuer_entity_id: Url,\n        name?: String,\n",
+		"        issuer_entity_id: Url,\n",
+		1,
+	);
+	let mut store = common::store();
+	store["schema"] = json!({ "body": schema });
+	let authz = Authz::new(common::config_with_store(&store)).unwrap();
+
+	let result = authz.handle_raw_input(&Input::demo().to_json());
+	assert!(result.is_ok(), "{:?}", result.err());
+
+	let entities = Input::demo().entities(&authz).unwrap();
+	let issuer = common::entity_json(&entities, "Jans::TrustedIssuer");
+	assert_eq!(issuer["attrs"].get("name"), None);
+	assert_eq!(
+		issuer["attrs"]["issuer_entity_id"]["host"],
+		"admin-ui-test.gluu.org"
+	);
+}
+
+#[test]
+fn policy_can_read_issuer_attributes() {
+	let mut store = common::store();
+	store["policies"] = serde_json::json!({
+		"gluu_clients": {
+			"body": "permit(principal, action, resource) when { resource.client.iss.issuer_entity_id.host == \"admin-ui-test.gluu.org\" && resource.client.iss has name && resource.client.iss.name == \"Gluu Admin UI\" };"
+		}
+	});
+
+	let authz = authz::Authz::new(common::config_with_store(&store)).unwrap();
+
+	let result = authz
+		.handle_raw_input(&Input::demo().to_json())
+		.unwrap()
+		.user
+		.unwrap();
+	assert!(
+		result.diagnostics().errors().next().is_none(),
+		"{:?}",
+		result.diagnostics()
+	);
+	assert_eq!(result.decision(), cedar_policy::Decision::Allow);
+}
+
+#[test]
+fn untrusted_token_kind_is_rejected() {
+	let authz = untrusted_authz(UntrustedTokenMode::Reject);
+
+	for (expected, field) in TOKEN_FIELDS {
+		let result = authz.handle_raw_input(&input_with_untrusted(field).to_json());
+		assert!(
+			matches!(
+				&result,
+				Err(HandleError::TrustedIssuer(TrustedIssuerError::UntrustedToken { kind, iss }))
+					if *kind == expected && iss == OTHER_ISSUER
+			),
+			"{field}: {:?}",
+			result.err()
+		);
+	}
+}
+
+#[test]
+fn ignored_token_has_no_influence_on_entities() {
+	let authz = untrusted_authz(UntrustedTokenMode::Ignore);
+
+	for (_, field) in TOKEN_FIELDS {
+		let input = input_with_untrusted(field);
+		let entities = entities_json(&input.to_json(), &authz);
+		assert_eq!(
+			entities,
+			entities_json(&input.to_json_without(&[field]), &authz),
+			"{field}"
+		);
+		let json = Value::from(entities).to_string();
+		assert!(!json.contains("intruder"), "{field}: {json}");
+		assert!(!json.contains(OTHER_ISSUER), "{field}: {json}");
+	}
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use authz::{
+	Authz, DecodeTokensError, HandleError, TokenKind, TrustedIssuerError, UntrustedTokenMode,
+};
+use cedar_policy::Decision;
+use common::Input;
+use serde_json::json;
+
+// demo store where the access is granted only to the calls of the service chain
+fn authz(untrusted_tokens: UntrustedTokenMode) -> Authz {
+	let mut store = common::store();
+	store["policies"] = json!({
+		"service_chain": {
+			"body": "permit(principal, action, resource) when { context has tx_token && context.tx_token.purp == \"execute\" && context.tx_token has rctx && context.tx_token.rctx has req_ip && context.tx_token.rctx.req_ip.isInRange(ip(\"192.168.0.0/16\")) };"
+		}
+	});
+
+	let mut config = common::config_with_store(&store);
+	config.untrusted_tokens = untrusted_tokens;
+	Authz::new(config).unwrap()
+}
+
+fn decision(authz: &Authz, input: &Input) -> Decision {
+	authz.handle_raw_input(&input.to_json()).unwrap().decision()
+}
+
+#[test]
+fn tx_token_is_available_in_context() {
+	let authz = authz(UntrustedTokenMode::default());
+
+	let mut input = Input::demo();
+	assert_eq!(decision(&authz, &input), Decision::Allow);
+
+	input.tx_token.as_mut().unwrap()["purp"] = "audit".into();
+	assert_eq!(decision(&authz, &input), Decision::Deny);
+
+	input.tx_token = None;
+	assert_eq!(decision(&authz, &input), Decision::Deny);
+}
+
+#[test]
+fn tx_token_entity_is_created_from_claims() {
+	let entities = Input::demo().entities(&common::authz()).unwrap();
+	let tx_token = common::entity_json(&entities, "Jans::Transaction_token");
+	assert_eq!(
+		tx_token["uid"]["id"],
+		"97053963-771d-49cc-a4e3-20aad399c312"
+	);
+	assert_eq!(tx_token["attrs"]["tctx"]["action"], "Execute");
+	assert_eq!(
+		tx_token["attrs"]["iss"]["__entity"]["id"],
+		"https://admin-ui-test.gluu.org"
+	);
+}
+
+#[test]
+fn tx_token_of_issuer_without_tx_tokens_config_is_not_trusted() {
+	let mut input = Input::demo();
+	input.tx_token.as_mut().unwrap()["iss"] = "https://accounts.google.com".into();
+
+	let result = authz(UntrustedTokenMode::Reject).handle_raw_input(&input.to_json());
+	assert!(
+		matches!(
+			&result,
+			Err(HandleError::TrustedIssuer(TrustedIssuerError::UntrustedToken { kind, .. }))
+				if *kind == TokenKind::TransactionToken
+		),
+		"{:?}",
+		result.err()
+	);
+
+	// ignored tx_token is not added to the context
+	assert_eq!(
+		decision(&authz(UntrustedTokenMode::Ignore), &input),
+		Decision::Deny
+	);
+}
+
+#[test]
+fn invalid_tx_token_is_reported() {
+	let mut input = Input::demo();
+	input.tx_token = None;
+	input.extra["tx_token"] = "eyJjbGc...".into();
+
+	let result = common::authz().handle_raw_input(&input.to_json());
+	assert!(
+		matches!(
+			&result,
+			Err(HandleError::DecodeTokens(
+				DecodeTokensError::TransactionToken(_)
+			))
+		),
+		"{:?}",
+		result.err()
+	);
+}
+
+#[test]
+fn tx_token_of_input_context_is_dropped() {
+	let mut store = common::store();
+	store["policies"] = json!({
+		"has_tx_token": { "body": "permit(principal, action, resource) when { context has tx_token };" }
+	});
+	let authz = |untrusted_tokens| {
+		let mut config = common::config_with_store(&store);
+		config.untrusted_tokens = untrusted_tokens;
+		Authz::new(config).unwrap()
+	};
+	let forged = json!({
+		"__entity": { "type": "Jans::Transaction_token", "id": "97053963-771d-49cc-a4e3-20aad399c312" }
+	});
+
+	let mut input = Input::demo();
+	input.extra["context"]["tx_token"] = forged.clone();
+	assert_eq!(
+		decision(&authz(UntrustedTokenMode::default()), &input),
+		Decision::Allow
+	);
+
+	input.tx_token = None;
+	assert_eq!(
+		decision(&authz(UntrustedTokenMode::default()), &input),
+		Decision::Deny
+	);
+
+	// ignored tx_token doesn't keep the value of the input
+	let mut input = Input::demo();
+	input.tx_token.as_mut().unwrap()["iss"] = "https://accounts.google.com".into();
+	input.extra["context"]["tx_token"] = forged;
+	assert_eq!(
+		decision(&authz(UntrustedTokenMode::Ignore), &input),
+		Decision::Deny
+	);
+}
+
+#[test]
+fn tx_token_is_added_only_if_schema_declares_it() {
+	let without_context_attr =
+		common::SCHEMA.replacen("            tx_token?: Transaction_token,\n", "", 1);
+	let (head, tail) = without_context_attr
+		.split_once("    entity Transaction_token  = {")
+		.unwrap();
+	let (_, tail) = tail.split_once("    };").unwrap();
+	let without_entity_type = format!("{head}{tail}");
+
+	for (schema, has_entity) in [
+		(without_context_attr.clone(), true),
+		(without_entity_type, false),
+	] {
+		let mut store = common::store();
+		store["schema"] = json!({ "body": schema });
+		let authz = Authz::new(common::config_with_store(&store)).unwrap();
+
+		let result = authz.handle_raw_input(&Input::demo().to_json());
+		assert!(result.is_ok(), "{:?}", result.err());
+		assert_eq!(result.unwrap().decision(), Decision::Allow);
+
+		let entities = Input::demo().entities(&authz).unwrap();
+		let tx_tokens = entities
+			.iter()
+			.filter(|e| e.uid().type_name().to_string() == "Jans::Transaction_token")
+			.count();
+		assert_eq!(tx_tokens, usize::from(has_entity));
+	}
+}
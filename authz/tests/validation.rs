@@ -0,0 +1,47 @@
+mod common;
+
+use authz::{Authz, AuthzNewError, ValidationWarningMode};
+use serde_json::json;
+
+// demo store with additional policy
+fn config(policy: &str, validation_warnings: ValidationWarningMode) -> authz::AuthzConfig {
+	let mut store = common::store();
+	store["policies"]["extra"] = json!({ "body": policy });
+
+	let mut config = common::config_with_store(&store);
+	config.validation_warnings = validation_warnings;
+	config
+}
+
+#[test]
+fn policy_with_typo_is_rejected() {
+	let policy =
+		"permit(principal is Jans::User, action, resource) when { principal.emial == \"admin\" };";
+	let result = Authz::new(config(policy, ValidationWarningMode::default()));
+
+	let Err(AuthzNewError::Validation(err)) = result else {
+		panic!("{:?}", result.err());
+	};
+	assert_eq!(err.store_id, authz::DEFAULT_POLICY_STORE_ID);
+	assert!(!err.issues.is_empty());
+	for issue in &err.issues {
+		assert_eq!(issue.policy_id, "extra");
+		let (start, end) = issue.location.expect("location of the cedar policy");
+		assert!(policy[start..end].contains("emial"), "{issue}");
+	}
+}
+
+#[test]
+fn warnings_are_fatal_by_config() {
+	let policy = "permit(principal, action, resource) when { false };";
+
+	let result = Authz::new(config(policy, ValidationWarningMode::Log));
+	assert!(result.is_ok(), "{:?}", result.err());
+
+	let result = Authz::new(config(policy, ValidationWarningMode::Deny));
+	assert!(
+		matches!(&result, Err(AuthzNewError::Validation(err)) if err.issues[0].policy_id == "extra"),
+		"{:?}",
+		result.err()
+	);
+}
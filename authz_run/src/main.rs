@@ -17,11 +17,11 @@ fn real_demo_case() -> Result<(), Box<dyn std::error::Error>> {
 
 	let input_json = include_str!("../../cedar_files/input.json");
 
-	let authz = Authz::new(AuthzConfig {
-		app_name: Some("Demo_App".to_owned()),
-		decoder: jwt::JWTDecoder::new_without_validation(),
-		policy: authz::PolicyStoreConfig::Local,
-	})?;
+	let authz = Authz::new(AuthzConfig::new(
+		Some("Demo_App".to_owned()),
+		jwt::JWTDecoder::new_without_validation(),
+		authz::PolicyStoreConfig::Local,
+	))?;
 
 	// only show entities for debug
 	{
@@ -39,7 +39,7 @@ fn real_demo_case() -> Result<(), Box<dyn std::error::Error>> {
 		}
 	}
 
-	let v = authz.handle_raw_input(&input_json)?;
+	let v = authz.handle_raw_input(input_json)?;
 	let decision = v.decision();
 	println!("decision: {decision:#?}");
 	Ok(())
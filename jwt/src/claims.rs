@@ -0,0 +1,108 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use super::DecodeError;
+
+/// Source of the current time used to check `exp`, `nbf` and `iat` claims.
+pub trait Clock: Send + Sync {
+	/// Current time as seconds since the Unix epoch.
+	fn now(&self) -> i64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> i64 {
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs() as i64)
+			.unwrap_or_default()
+	}
+}
+
+pub(crate) struct ClaimsValidation<'a> {
+	pub(crate) leeway: u64,
+	pub(crate) required_claims: &'a [String],
+	pub(crate) audiences: &'a [String],
+	pub(crate) issuers: &'a [String],
+	pub(crate) now: i64,
+}
+
+fn numeric_claim(claims: &Value, name: &str) -> Result<Option<i64>, DecodeError> {
+	match claims.get(name) {
+		None | Some(Value::Null) => Ok(None),
+		Some(value) => value
+			.as_i64()
+			// NumericDate can have fraction part
+			.or_else(|| value.as_f64().map(|v| v as i64))
+			.map(Some)
+			.ok_or_else(|| DecodeError::InvalidClaimType(name.to_owned())),
+	}
+}
+
+// `aud` can be single string or array of strings
+fn audience_claim(claims: &Value) -> Result<Vec<&str>, DecodeError> {
+	let invalid = || DecodeError::InvalidClaimType("aud".to_owned());
+	match claims.get("aud") {
+		None | Some(Value::Null) => Ok(Vec::new()),
+		Some(Value::String(aud)) => Ok(vec![aud.as_str()]),
+		Some(Value::Array(list)) => list
+			.iter()
+			.map(|v| v.as_str().ok_or_else(invalid))
+			.collect(),
+		Some(_) => Err(invalid()),
+	}
+}
+
+impl ClaimsValidation<'_> {
+	pub(crate) fn validate(&self, claims: &Value) -> Result<(), DecodeError> {
+		for name in self.required_claims {
+			if claims.get(name).is_none_or(Value::is_null) {
+				return Err(DecodeError::MissingClaim(name.to_owned()));
+			}
+		}
+
+		let leeway = self.leeway as i64;
+		if let Some(exp) = numeric_claim(claims, "exp")? {
+			if exp + leeway <= self.now {
+				return Err(DecodeError::Expired(exp));
+			}
+		}
+		if let Some(nbf) = numeric_claim(claims, "nbf")? {
+			if nbf - leeway > self.now {
+				return Err(DecodeError::NotYetValid(nbf));
+			}
+		}
+		if let Some(iat) = numeric_claim(claims, "iat")? {
+			if iat - leeway > self.now {
+				return Err(DecodeError::IssuedInFuture(iat));
+			}
+		}
+
+		if !self.issuers.is_empty() {
+			let iss = match claims.get("iss") {
+				Some(Value::String(iss)) => iss,
+				Some(_) => return Err(DecodeError::InvalidClaimType("iss".to_owned())),
+				None => return Err(DecodeError::MissingClaim("iss".to_owned())),
+			};
+			if !self.issuers.contains(iss) {
+				return Err(DecodeError::InvalidIssuer(iss.to_owned()));
+			}
+		}
+
+		if !self.audiences.is_empty() {
+			let aud = audience_claim(claims)?;
+			if aud.is_empty() {
+				return Err(DecodeError::MissingClaim("aud".to_owned()));
+			}
+			if !aud.iter().any(|a| self.audiences.iter().any(|e| e == a)) {
+				return Err(DecodeError::InvalidAudience(
+					aud.into_iter().map(str::to_owned).collect(),
+				));
+			}
+		}
+
+		Ok(())
+	}
+}
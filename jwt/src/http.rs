@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug)]
+#[error("GET {url} failed: {message}")]
+pub struct HttpError {
+	pub url: String,
+	pub message: String,
+}
+
+/// Response of the request which can be revalidated by ETag.
+pub enum HttpResponse {
+	/// new version of the document
+	Modified { body: String, etag: Option<String> },
+	/// document is not changed since the version with sent ETag
+	NotModified,
+}
+
+/// HTTP transport used to fetch issuer metadata and remote documents,
+/// can be replaced with stub in tests.
+pub trait HttpClient: Send + Sync {
+	/// Get document by url, `etag` of the cached copy is sent as `If-None-Match` header.
+	fn get_if_none_match(&self, url: &str, etag: Option<&str>) -> Result<HttpResponse, HttpError>;
+
+	/// Get body of the document by url.
+	fn get(&self, url: &str) -> Result<String, HttpError> {
+		match self.get_if_none_match(url, None)? {
+			HttpResponse::Modified { body, .. } => Ok(body),
+			HttpResponse::NotModified => Err(HttpError {
+				url: url.to_owned(),
+				message: "server responded not modified without ETag".to_owned(),
+			}),
+		}
+	}
+}
+
+/// Default [`HttpClient`] based on `ureq`.
+pub struct UreqClient {
+	agent: ureq::Agent,
+}
+
+impl Default for UreqClient {
+	fn default() -> Self {
+		Self {
+			agent: ureq::AgentBuilder::new()
+				.timeout(Duration::from_secs(10))
+				.build(),
+		}
+	}
+}
+
+impl HttpClient for UreqClient {
+	fn get_if_none_match(&self, url: &str, etag: Option<&str>) -> Result<HttpResponse, HttpError> {
+		let to_error = |err: &dyn std::error::Error| HttpError {
+			url: url.to_owned(),
+			message: err.to_string(),
+		};
+
+		let mut request = self.agent.get(url);
+		if let Some(etag) = etag {
+			request = request.set("If-None-Match", etag);
+		}
+
+		let response = request.call().map_err(|err| to_error(&err))?;
+		if response.status() == 304 {
+			return Ok(HttpResponse::NotModified);
+		}
+
+		let etag = response.header("ETag").map(str::to_owned);
+		let body = response.into_string().map_err(|err| to_error(&err))?;
+		Ok(HttpResponse::Modified { body, etag })
+	}
+}
@@ -0,0 +1,198 @@
+use std::{borrow::Cow, collections::HashMap, path::Path, str::FromStr};
+
+use jsonwebtoken::{
+	jwk::{AlgorithmParameters, EllipticCurve, Jwk},
+	Algorithm, DecodingKey, Header,
+};
+
+use super::{DecodeError, IssuerResolver};
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeySetError {
+	#[error("could not read JWKS file: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("could not parse JWKS json: {0}")]
+	ParseJson(#[from] serde_json::Error),
+}
+
+// raw JWKS document, keys are parsed one by one
+// to skip keys that we can't use (encryption keys, unknown `kty`)
+#[derive(serde::Deserialize)]
+struct RawKeySet {
+	keys: Vec<serde_json::Value>,
+}
+
+struct KeyEntry {
+	kid: Option<String>,
+	// value of `alg` parameter, if key declares it
+	alg: Option<Algorithm>,
+	params: AlgorithmParameters,
+	key: DecodingKey,
+}
+
+impl KeyEntry {
+	fn from_jwk(jwk: Jwk) -> Result<Self, jsonwebtoken::errors::Error> {
+		let alg = match jwk.common.key_algorithm {
+			Some(key_alg) => Some(Algorithm::from_str(&key_alg.to_string())?),
+			None => None,
+		};
+		Ok(Self {
+			key: DecodingKey::from_jwk(&jwk)?,
+			kid: jwk.common.key_id,
+			alg,
+			params: jwk.algorithm,
+		})
+	}
+
+	// check that key can be used to verify JWT signed with `alg`
+	fn accepts(&self, alg: Algorithm) -> bool {
+		if let Some(key_alg) = self.alg {
+			return key_alg == alg;
+		}
+
+		match &self.params {
+			AlgorithmParameters::RSA(_) => {
+				matches!(
+					alg,
+					Algorithm::RS256
+						| Algorithm::RS384 | Algorithm::RS512
+						| Algorithm::PS256 | Algorithm::PS384
+						| Algorithm::PS512
+				)
+			}
+			// the curve defines the algorithm: P-256 is used only by ES256, P-384 by ES384
+			AlgorithmParameters::EllipticCurve(params) => match params.curve {
+				EllipticCurve::P256 => alg == Algorithm::ES256,
+				EllipticCurve::P384 => alg == Algorithm::ES384,
+				EllipticCurve::P521 | EllipticCurve::Ed25519 => false,
+			},
+			AlgorithmParameters::OctetKeyPair(_) => alg == Algorithm::EdDSA,
+			AlgorithmParameters::OctetKey(_) => {
+				matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+			}
+		}
+	}
+}
+
+/// Set of JSON Web Keys (RFC 7517), key is selected by `kid` and `alg` of the JWT header.
+pub struct KeySet {
+	keys: Vec<KeyEntry>,
+}
+
+impl KeySet {
+	pub fn from_json_str(json: &str) -> Result<Self, KeySetError> {
+		let raw: RawKeySet = serde_json::from_str(json)?;
+
+		let keys = raw
+			.keys
+			.into_iter()
+			.filter_map(|value| {
+				let entry = serde_json::from_value::<Jwk>(value.clone())
+					.map_err(|err| err.to_string())
+					.and_then(|jwk| KeyEntry::from_jwk(jwk).map_err(|err| err.to_string()));
+				match entry {
+					Ok(entry) => Some(entry),
+					Err(err) => {
+						log::warn!(
+							"skip JWK that can't be used to verify JWT: {err}, key: {value}"
+						);
+						None
+					}
+				}
+			})
+			.collect();
+
+		Ok(Self { keys })
+	}
+
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, KeySetError> {
+		let json = std::fs::read_to_string(path)?;
+		Self::from_json_str(&json)
+	}
+
+	pub fn len(&self) -> usize {
+		self.keys.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
+	}
+
+	/// Find key to verify JWT with given header.
+	///
+	/// If header has `kid` key must have the same id,
+	/// otherwise first key compatible with `alg` is used.
+	pub fn find(&self, header: &Header) -> Option<&DecodingKey> {
+		self.keys
+			.iter()
+			.filter(|entry| entry.accepts(header.alg))
+			.find(|entry| match &header.kid {
+				Some(kid) => entry.kid.as_ref() == Some(kid),
+				None => true,
+			})
+			.map(|entry| &entry.key)
+	}
+}
+
+/// Source of keys used to verify JWT signature.
+pub enum KeySource {
+	/// one key for every token
+	Key(DecodingKey),
+	/// key is selected from the key set by JWT header
+	KeySet(KeySet),
+	/// each trusted issuer has own key set, selected by `iss` claim
+	Issuers(HashMap<String, KeySet>),
+	/// key sets are fetched from the OpenID configuration of the issuer
+	Resolver(IssuerResolver),
+}
+
+impl From<DecodingKey> for KeySource {
+	fn from(key: DecodingKey) -> Self {
+		Self::Key(key)
+	}
+}
+
+impl From<KeySet> for KeySource {
+	fn from(key_set: KeySet) -> Self {
+		Self::KeySet(key_set)
+	}
+}
+
+// used to get issuer of the token before signature is verified
+#[derive(serde::Deserialize)]
+struct IssuerClaim {
+	iss: Option<String>,
+}
+
+fn issuer(jwt: &str) -> Result<String, DecodeError> {
+	let claim: IssuerClaim = super::decode_jwt_without_validation(jwt)?;
+	claim.iss.ok_or(DecodeError::MissingIssuer)
+}
+
+impl KeySource {
+	pub(crate) fn select(
+		&self,
+		jwt: &str,
+		header: &Header,
+	) -> Result<Cow<'_, DecodingKey>, DecodeError> {
+		let key_set = match self {
+			Self::Key(key) => return Ok(Cow::Borrowed(key)),
+			Self::KeySet(key_set) => key_set,
+			Self::Issuers(issuers) => {
+				let iss = issuer(jwt)?;
+				issuers.get(&iss).ok_or(DecodeError::UnknownIssuer(iss))?
+			}
+			Self::Resolver(resolver) => {
+				return resolver.decoding_key(&issuer(jwt)?, header).map(Cow::Owned)
+			}
+		};
+
+		key_set
+			.find(header)
+			.map(Cow::Borrowed)
+			.ok_or_else(|| DecodeError::KeyNotFound {
+				kid: header.kid.clone(),
+				alg: header.alg,
+			})
+	}
+}
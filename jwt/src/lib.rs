@@ -1,10 +1,59 @@
 use std::string::FromUtf8Error;
 
 use base64::prelude::*;
+pub use jsonwebtoken::{Algorithm, DecodingKey};
 
-// TODO: implement
-pub struct JWTValidationConfig {}
+mod claims;
+pub use claims::*;
+mod http;
+pub use http::*;
+mod jwks;
+pub use jwks::*;
+mod openid;
+pub use openid::*;
 
+/// Algorithms which can be used to verify JWT signature.
+pub const SUPPORTED_ALGORITHMS: [Algorithm; 4] = [
+	Algorithm::RS256,
+	Algorithm::ES256,
+	Algorithm::EdDSA,
+	Algorithm::HS256,
+];
+
+pub struct JWTValidationConfig {
+	// algorithms that are accepted in the JWT header,
+	// each of them should be from `SUPPORTED_ALGORITHMS`
+	pub algorithms: Vec<Algorithm>,
+	// keys used to verify signature, should match the family of accepted algorithms
+	pub keys: KeySource,
+	// allowed clock skew in seconds for `exp`, `nbf` and `iat` checks
+	pub leeway: u64,
+	// claims that must be present in the token
+	pub required_claims: Vec<String>,
+	// `aud` must contain one of them, not checked if empty
+	pub audiences: Vec<String>,
+	// `iss` must be one of them, not checked if empty
+	pub issuers: Vec<String>,
+	// source of the current time, can be replaced in tests
+	pub clock: Box<dyn Clock>,
+}
+
+impl JWTValidationConfig {
+	/// Config which checks signature and `exp`, `nbf`, `iat` claims if they are present.
+	pub fn new(algorithms: Vec<Algorithm>, keys: impl Into<KeySource>) -> Self {
+		Self {
+			algorithms,
+			keys: keys.into(),
+			leeway: 60,
+			required_claims: Vec::new(),
+			audiences: Vec::new(),
+			issuers: Vec::new(),
+			clock: Box::new(SystemClock),
+		}
+	}
+}
+
+#[allow(clippy::large_enum_variant)]
 pub enum JWTDecoder {
 	WithValidation(JWTValidationConfig),
 	WithoutValidation,
@@ -14,12 +63,27 @@ impl JWTDecoder {
 	pub fn new_without_validation() -> Self {
 		Self::WithoutValidation
 	}
+
+	pub fn new_with_validation(config: JWTValidationConfig) -> Self {
+		Self::WithValidation(config)
+	}
+
+	/// Resolver of trusted issuer keys, if keys are fetched from OpenID configuration.
+	pub fn issuer_resolver(&self) -> Option<&IssuerResolver> {
+		match self {
+			JWTDecoder::WithValidation(JWTValidationConfig {
+				keys: KeySource::Resolver(resolver),
+				..
+			}) => Some(resolver),
+			_ => None,
+		}
+	}
 }
 
 impl JWTDecoder {
 	pub fn decode<T: serde::de::DeserializeOwned>(&self, jwt: &str) -> Result<T, DecodeError> {
 		match self {
-			JWTDecoder::WithValidation(_config) => todo!(),
+			JWTDecoder::WithValidation(config) => decode_jwt_with_validation(jwt, config),
 			JWTDecoder::WithoutValidation => decode_jwt_without_validation(jwt),
 		}
 	}
@@ -35,17 +99,89 @@ pub enum DecodeError {
 	UnableToString(#[from] FromUtf8Error),
 	#[error("Unable to parse JWT JSON data: {0}, payload: {1}")]
 	UnableToParseJson(serde_json::Error, String),
+	#[error("Unable to parse JWT header: {0}")]
+	InvalidHeader(jsonwebtoken::errors::Error),
+	#[error("JWT signed with unsupported algorithm: {0:?}")]
+	UnsupportedAlgorithm(Algorithm),
+	#[error("JWT signature is invalid")]
+	InvalidSignature,
+	#[error("Key could not be used to verify JWT signed with algorithm: {0:?}")]
+	KeyMismatch(Algorithm),
+	#[error("No key found to verify JWT with kid: {kid:?} and algorithm: {alg:?}")]
+	KeyNotFound { kid: Option<String>, alg: Algorithm },
+	#[error("JWT does not have `iss` claim to select key set")]
+	MissingIssuer,
+	#[error("JWT issued by unknown issuer: {0}")]
+	UnknownIssuer(String),
+	#[error("Unable to get keys of JWT issuer: {0}")]
+	IssuerMetadata(ResolveError),
+	#[error("JWT does not have required claim: {0}")]
+	MissingClaim(String),
+	#[error("JWT claim `{0}` has invalid type")]
+	InvalidClaimType(String),
+	#[error("JWT is expired at: {0}")]
+	Expired(i64),
+	#[error("JWT is not valid before: {0}")]
+	NotYetValid(i64),
+	#[error("JWT is issued in the future at: {0}")]
+	IssuedInFuture(i64),
+	#[error("JWT is issued by not expected issuer: {0}")]
+	InvalidIssuer(String),
+	#[error("JWT is issued for not expected audience: {0:?}")]
+	InvalidAudience(Vec<String>),
+	#[error("Unable to validate JWT: {0}")]
+	Validation(jsonwebtoken::errors::Error),
 }
 
 pub fn decode_jwt_without_validation<T: serde::de::DeserializeOwned>(
 	jwt: &str,
 ) -> Result<T, DecodeError> {
 	let payload_base64 = jwt.split('.').nth(1).ok_or(DecodeError::MalformedJWT)?;
-	let payload_json = BASE64_STANDARD_NO_PAD
+	let payload_json = BASE64_URL_SAFE_NO_PAD
 		.decode(payload_base64)
 		.map_err(|err| DecodeError::UnableToDecodeBase64(err, payload_base64.to_owned()))?;
 
 	let payload_json = String::from_utf8(payload_json)?;
-	Ok(serde_json::from_str(payload_json.as_str())
-		.map_err(|err| DecodeError::UnableToParseJson(err, payload_json.to_owned()))?)
+	serde_json::from_str(payload_json.as_str())
+		.map_err(|err| DecodeError::UnableToParseJson(err, payload_json.to_owned()))
+}
+
+pub fn decode_jwt_with_validation<T: serde::de::DeserializeOwned>(
+	jwt: &str,
+	config: &JWTValidationConfig,
+) -> Result<T, DecodeError> {
+	let header = jsonwebtoken::decode_header(jwt).map_err(DecodeError::InvalidHeader)?;
+	if !SUPPORTED_ALGORITHMS.contains(&header.alg) || !config.algorithms.contains(&header.alg) {
+		return Err(DecodeError::UnsupportedAlgorithm(header.alg));
+	}
+
+	let key = config.keys.select(jwt, &header)?;
+
+	// only signature is verified here, registered claims are checked by `ClaimsValidation`
+	let mut validation = jsonwebtoken::Validation::new(header.alg);
+	validation.required_spec_claims.clear();
+	validation.validate_exp = false;
+	validation.validate_aud = false;
+
+	let token = jsonwebtoken::decode::<serde_json::Value>(jwt, &key, &validation).map_err(
+		|err| match err.kind() {
+			jsonwebtoken::errors::ErrorKind::InvalidSignature => DecodeError::InvalidSignature,
+			jsonwebtoken::errors::ErrorKind::InvalidAlgorithm => {
+				DecodeError::KeyMismatch(header.alg)
+			}
+			_ => DecodeError::Validation(err),
+		},
+	)?;
+
+	ClaimsValidation {
+		leeway: config.leeway,
+		required_claims: &config.required_claims,
+		audiences: &config.audiences,
+		issuers: &config.issuers,
+		now: config.clock.now(),
+	}
+	.validate(&token.claims)?;
+
+	serde_json::from_value(token.claims.clone())
+		.map_err(|err| DecodeError::UnableToParseJson(err, token.claims.to_string()))
 }
@@ -0,0 +1,316 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use jsonwebtoken::{DecodingKey, Header};
+
+use super::{DecodeError, HttpClient, HttpError, KeySet, KeySetError};
+
+/// Fields of OpenID Provider metadata document that we use.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct OpenIdConfiguration {
+	pub issuer: String,
+	pub jwks_uri: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResolveError {
+	#[error("could not fetch issuer metadata: {0}")]
+	Http(#[from] HttpError),
+	#[error("could not parse openid configuration from {0}: {1}")]
+	ParseOpenIdConfiguration(String, serde_json::Error),
+	#[error("openid configuration {endpoint} belongs to other issuer: {issuer}")]
+	IssuerMismatch { endpoint: String, issuer: String },
+	#[error("could not parse JWKS from {0}: {1}")]
+	ParseKeySet(String, KeySetError),
+	#[error("JWKS {0} is not available, it is fetched again after the retry delay")]
+	KeySetDelayed(String),
+}
+
+pub struct IssuerResolverConfig {
+	// how long fetched JWKS is used before it is downloaded again
+	pub ttl: Duration,
+	// minimal interval between the last JWKS download attempt and the download caused by unknown `kid`
+	pub refresh_cooldown: Duration,
+	// delay before openid configuration or JWKS of unavailable issuer is fetched again,
+	// doubled after every failed attempt up to `ttl`
+	pub retry_backoff: Duration,
+}
+
+impl Default for IssuerResolverConfig {
+	fn default() -> Self {
+		Self {
+			ttl: Duration::from_secs(60 * 60),
+			refresh_cooldown: Duration::from_secs(60),
+			retry_backoff: Duration::from_secs(30),
+		}
+	}
+}
+
+struct CachedKeySet {
+	key_set: KeySet,
+	fetched_at: Instant,
+}
+
+struct IssuerMetadata {
+	jwks_uri: String,
+	// shared to find the key without holding the lock
+	keys: Option<Arc<CachedKeySet>>,
+	// number of JWKS fetches started since the last successful one
+	key_set_attempts: u32,
+	// JWKS is not fetched before this moment
+	key_set_retry_at: Instant,
+	// start of the last JWKS fetch
+	key_set_fetched_at: Option<Instant>,
+}
+
+struct PendingEndpoint {
+	endpoint: String,
+	// number of fetches started, the entry is removed when the fetch succeeds
+	attempts: u32,
+	// endpoint is not fetched before this moment
+	retry_at: Instant,
+}
+
+#[derive(Default)]
+struct ResolverState {
+	// openid configuration endpoints which are not fetched yet
+	pending: Vec<PendingEndpoint>,
+	// openid configuration endpoints which are already fetched
+	resolved: Vec<String>,
+	// fetched metadata keyed by `issuer`
+	issuers: HashMap<String, IssuerMetadata>,
+}
+
+/// Resolves keys of trusted issuers using the OpenID discovery document.
+///
+/// Discovery documents are fetched when a token of an unknown issuer arrives,
+/// JWKS is cached for `ttl` and downloaded again when the token has unknown `kid`.
+/// HTTP requests are made without holding the lock, so a slow issuer doesn't block tokens of other issuers.
+pub struct IssuerResolver {
+	http: Box<dyn HttpClient>,
+	config: IssuerResolverConfig,
+	state: Mutex<ResolverState>,
+}
+
+impl IssuerResolver {
+	pub fn new(http: Box<dyn HttpClient>, config: IssuerResolverConfig) -> Self {
+		Self {
+			http,
+			config,
+			state: Mutex::new(ResolverState::default()),
+		}
+	}
+
+	/// Register `openid_configuration_endpoint` of the trusted issuer,
+	/// endpoints registered before are ignored.
+	pub fn add_endpoint(&self, endpoint: &str) {
+		let mut state = self.lock();
+		let known = state
+			.pending
+			.iter()
+			.map(|pending| &pending.endpoint)
+			.chain(&state.resolved)
+			.any(|e| e == endpoint);
+		if !known {
+			state.pending.push(PendingEndpoint {
+				endpoint: endpoint.to_owned(),
+				attempts: 0,
+				retry_at: Instant::now(),
+			});
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, ResolverState> {
+		// the state is consistent after each modification, so poisoned lock can be reused
+		self.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn fetch_configuration(&self, endpoint: &str) -> Result<OpenIdConfiguration, ResolveError> {
+		let body = self.http.get(endpoint)?;
+		let configuration: OpenIdConfiguration = serde_json::from_str(&body)
+			.map_err(|err| ResolveError::ParseOpenIdConfiguration(endpoint.to_owned(), err))?;
+
+		// discovery document is published by the issuer under its own url (OpenID Connect Discovery 1.0, 4.3)
+		let expected = format!(
+			"{}/.well-known/openid-configuration",
+			configuration.issuer.trim_end_matches('/')
+		);
+		if endpoint != expected {
+			return Err(ResolveError::IssuerMismatch {
+				endpoint: endpoint.to_owned(),
+				issuer: configuration.issuer,
+			});
+		}
+		Ok(configuration)
+	}
+
+	fn fetch_key_set(&self, jwks_uri: &str) -> Result<CachedKeySet, ResolveError> {
+		let body = self.http.get(jwks_uri)?;
+		let key_set = KeySet::from_json_str(&body)
+			.map_err(|err| ResolveError::ParseKeySet(jwks_uri.to_owned(), err))?;
+		Ok(CachedKeySet {
+			key_set,
+			fetched_at: Instant::now(),
+		})
+	}
+
+	// delay after `attempts` failed fetches of openid configuration or JWKS
+	fn retry_delay(&self, attempts: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+		self.config
+			.retry_backoff
+			.saturating_mul(factor)
+			.min(self.config.ttl)
+	}
+
+	// fetch openid configuration of endpoints that are not resolved yet and whose backoff is elapsed,
+	// failed endpoints stay pending and are retried after the next delay
+	fn resolve_pending(&self) {
+		let due: Vec<String> = {
+			let mut state = self.lock();
+			let now = Instant::now();
+			let mut due = Vec::new();
+			for pending in state.pending.iter_mut().filter(|p| p.retry_at <= now) {
+				// next attempt is scheduled before the fetch, so concurrent callers skip the endpoint
+				pending.attempts += 1;
+				pending.retry_at = now + self.retry_delay(pending.attempts);
+				due.push(pending.endpoint.clone());
+			}
+			due
+		};
+
+		for endpoint in due {
+			match self.fetch_configuration(&endpoint) {
+				Ok(configuration) => {
+					let mut state = self.lock();
+					state.pending.retain(|pending| pending.endpoint != endpoint);
+					state.issuers.insert(
+						configuration.issuer,
+						IssuerMetadata {
+							jwks_uri: configuration.jwks_uri,
+							keys: None,
+							key_set_attempts: 0,
+							key_set_retry_at: Instant::now(),
+							key_set_fetched_at: None,
+						},
+					);
+					state.resolved.push(endpoint);
+				}
+				Err(err) => log::warn!("could not resolve trusted issuer metadata: {err}"),
+			}
+		}
+	}
+
+	// JWKS uri and cached keys of the issuer
+	fn issuer_metadata(&self, iss: &str) -> Option<(String, Option<Arc<CachedKeySet>>)> {
+		self.lock()
+			.issuers
+			.get(iss)
+			.map(|metadata| (metadata.jwks_uri.clone(), metadata.keys.clone()))
+	}
+
+	// check that JWKS of the issuer can be fetched now and schedule the next attempt before the fetch,
+	// so concurrent callers and tokens with random `kid` of unavailable issuer don't cause more requests
+	fn start_key_set_fetch(&self, iss: &str, unknown_kid: bool) -> bool {
+		let mut state = self.lock();
+		let Some(metadata) = state.issuers.get_mut(iss) else {
+			return false;
+		};
+		let now = Instant::now();
+		let cooldown = unknown_kid
+			&& metadata
+				.key_set_fetched_at
+				.is_some_and(|at| now.duration_since(at) < self.config.refresh_cooldown);
+		if cooldown || metadata.key_set_retry_at > now {
+			return false;
+		}
+		metadata.key_set_attempts += 1;
+		metadata.key_set_retry_at = now + self.retry_delay(metadata.key_set_attempts);
+		metadata.key_set_fetched_at = Some(now);
+		true
+	}
+
+	fn fetch_and_store_keys(
+		&self,
+		iss: &str,
+		jwks_uri: &str,
+	) -> Result<Arc<CachedKeySet>, ResolveError> {
+		let keys = Arc::new(self.fetch_key_set(jwks_uri)?);
+		if let Some(metadata) = self.lock().issuers.get_mut(iss) {
+			metadata.keys = Some(keys.clone());
+			metadata.key_set_attempts = 0;
+			metadata.key_set_retry_at = Instant::now();
+		}
+		Ok(keys)
+	}
+
+	// fetch JWKS if the schedule of the issuer allows it,
+	// `stale` keys are kept if the fetch is delayed or fails
+	fn refresh_keys(
+		&self,
+		iss: &str,
+		jwks_uri: &str,
+		stale: Option<Arc<CachedKeySet>>,
+		unknown_kid: bool,
+	) -> Result<Arc<CachedKeySet>, ResolveError> {
+		if !self.start_key_set_fetch(iss, unknown_kid) {
+			return stale.ok_or_else(|| ResolveError::KeySetDelayed(jwks_uri.to_owned()));
+		}
+		match (self.fetch_and_store_keys(iss, jwks_uri), stale) {
+			(Ok(keys), _) => Ok(keys),
+			(Err(err), Some(stale)) => {
+				log::warn!("could not refresh JWKS of {iss}: {err}");
+				Ok(stale)
+			}
+			(Err(err), None) => Err(err),
+		}
+	}
+
+	/// Find key to verify JWT of the issuer `iss` with given header.
+	pub fn decoding_key(&self, iss: &str, header: &Header) -> Result<DecodingKey, DecodeError> {
+		let metadata = match self.issuer_metadata(iss) {
+			Some(metadata) => metadata,
+			None => {
+				self.resolve_pending();
+				self.issuer_metadata(iss)
+					.ok_or_else(|| DecodeError::UnknownIssuer(iss.to_owned()))?
+			}
+		};
+		let (jwks_uri, keys) = metadata;
+
+		let expired = keys
+			.as_ref()
+			.is_none_or(|keys| keys.fetched_at.elapsed() >= self.config.ttl);
+		let mut keys = match keys {
+			Some(keys) if !expired => keys,
+			// stale keys are used if the issuer is not available
+			stale => self
+				.refresh_keys(iss, &jwks_uri, stale, false)
+				.map_err(DecodeError::IssuerMetadata)?,
+		};
+
+		if let Some(key) = keys.key_set.find(header) {
+			return Ok(key.clone());
+		}
+
+		// key could be rotated by the issuer
+		if !expired {
+			keys = self
+				.refresh_keys(iss, &jwks_uri, Some(keys), true)
+				.map_err(DecodeError::IssuerMetadata)?;
+		}
+
+		keys.key_set
+			.find(header)
+			.cloned()
+			.ok_or_else(|| DecodeError::KeyNotFound {
+				kid: header.kid.clone(),
+				alg: header.alg,
+			})
+	}
+}
@@ -0,0 +1,139 @@
+use jsonwebtoken::{encode, EncodingKey, Header};
+use jwt::{
+	decode_jwt_with_validation, Algorithm, Clock, DecodeError, DecodingKey, JWTValidationConfig,
+};
+
+const SECRET: &[u8] = b"test secret of hmac signed tokens";
+const NOW: i64 = 1_725_018_932;
+
+struct FixedClock(i64);
+
+impl Clock for FixedClock {
+	fn now(&self) -> i64 {
+		self.0
+	}
+}
+
+fn config() -> JWTValidationConfig {
+	let mut config =
+		JWTValidationConfig::new(vec![Algorithm::HS256], DecodingKey::from_secret(SECRET));
+	config.clock = Box::new(FixedClock(NOW));
+	config
+}
+
+fn decode(claims: serde_json::Value, config: &JWTValidationConfig) -> Result<(), DecodeError> {
+	let jwt = encode(
+		&Header::new(Algorithm::HS256),
+		&claims,
+		&EncodingKey::from_secret(SECRET),
+	)
+	.unwrap();
+	decode_jwt_with_validation::<serde_json::Value>(&jwt, config).map(|_| ())
+}
+
+#[test]
+fn temporal_claims_are_checked_with_leeway() {
+	let config = config();
+	let leeway = config.leeway as i64;
+
+	let cases = [
+		(
+			serde_json::json!({ "exp": NOW + 10, "iat": NOW, "nbf": NOW }),
+			None,
+		),
+		// inside of the leeway
+		(serde_json::json!({ "exp": NOW - leeway + 1 }), None),
+		(serde_json::json!({ "nbf": NOW + leeway }), None),
+		(serde_json::json!({ "iat": NOW + leeway }), None),
+		(serde_json::json!({ "exp": NOW - leeway }), Some("exp")),
+		(serde_json::json!({ "nbf": NOW + leeway + 1 }), Some("nbf")),
+		(serde_json::json!({ "iat": NOW + leeway + 1 }), Some("iat")),
+	];
+
+	for (claims, failed) in cases {
+		let result = decode(claims.clone(), &config);
+		match failed {
+			None => assert!(result.is_ok(), "{claims}: {result:?}"),
+			Some("exp") => assert!(matches!(result, Err(DecodeError::Expired(_))), "{result:?}"),
+			Some("nbf") => assert!(
+				matches!(result, Err(DecodeError::NotYetValid(_))),
+				"{result:?}"
+			),
+			Some(_) => assert!(
+				matches!(result, Err(DecodeError::IssuedInFuture(_))),
+				"{result:?}"
+			),
+		}
+	}
+}
+
+#[test]
+fn temporal_claim_with_invalid_type_is_rejected() {
+	let result = decode(serde_json::json!({ "exp": "tomorrow" }), &config());
+	assert!(
+		matches!(&result, Err(DecodeError::InvalidClaimType(claim)) if claim == "exp"),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn required_claims_are_checked() {
+	let mut config = config();
+	config.required_claims = vec!["exp".to_owned(), "sub".to_owned()];
+
+	let result = decode(
+		serde_json::json!({ "exp": NOW + 10, "sub": "alice" }),
+		&config,
+	);
+	assert!(result.is_ok(), "{result:?}");
+
+	let result = decode(serde_json::json!({ "exp": NOW + 10 }), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::MissingClaim(claim)) if claim == "sub"),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn issuer_is_checked() {
+	let mut config = config();
+	config.issuers = vec!["https://issuer.test".to_owned()];
+
+	let result = decode(serde_json::json!({ "iss": "https://issuer.test" }), &config);
+	assert!(result.is_ok(), "{result:?}");
+
+	let result = decode(serde_json::json!({ "iss": "https://other.test" }), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::InvalidIssuer(iss)) if iss == "https://other.test"),
+		"{result:?}"
+	);
+
+	let result = decode(serde_json::json!({}), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::MissingClaim(claim)) if claim == "iss"),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn audience_is_checked() {
+	let mut config = config();
+	config.audiences = vec!["client".to_owned()];
+
+	let result = decode(serde_json::json!({ "aud": "client" }), &config);
+	assert!(result.is_ok(), "{result:?}");
+	let result = decode(serde_json::json!({ "aud": ["other", "client"] }), &config);
+	assert!(result.is_ok(), "{result:?}");
+
+	let result = decode(serde_json::json!({ "aud": ["other"] }), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::InvalidAudience(aud)) if aud == &["other"]),
+		"{result:?}"
+	);
+
+	let result = decode(serde_json::json!({}), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::MissingClaim(claim)) if claim == "aud"),
+		"{result:?}"
+	);
+}
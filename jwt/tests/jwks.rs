@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use jwt::{
+	decode_jwt_with_validation, Algorithm, DecodeError, JWTValidationConfig, KeySet, KeySource,
+	SUPPORTED_ALGORITHMS,
+};
+
+const JWKS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwks.json");
+
+fn claims(iss: &str) -> serde_json::Value {
+	serde_json::json!({ "iss": iss, "sub": "alice" })
+}
+
+fn rsa_key() -> EncodingKey {
+	EncodingKey::from_rsa_pem(include_bytes!("fixtures/rsa_private.pem")).unwrap()
+}
+
+fn signed(alg: Algorithm, kid: Option<&str>, key: &EncodingKey, iss: &str) -> String {
+	let mut header = Header::new(alg);
+	header.kid = kid.map(str::to_owned);
+	encode(&header, &claims(iss), key).unwrap()
+}
+
+fn config(keys: KeySource) -> JWTValidationConfig {
+	JWTValidationConfig::new(SUPPORTED_ALGORITHMS.to_vec(), keys)
+}
+
+#[test]
+fn key_set_skips_unusable_keys() {
+	let key_set = KeySet::from_file(JWKS_PATH).unwrap();
+	// encryption key with `RSA-OAEP` algorithm is skipped
+	assert_eq!(key_set.len(), 4);
+}
+
+#[test]
+fn key_is_selected_by_kid() {
+	let keys = [
+		(Algorithm::RS256, "rsa_sig_rs256", rsa_key()),
+		(
+			Algorithm::ES256,
+			"ec_sig",
+			EncodingKey::from_ec_pem(include_bytes!("fixtures/ec_private.pem")).unwrap(),
+		),
+		(
+			Algorithm::EdDSA,
+			"ed_sig",
+			EncodingKey::from_ed_pem(include_bytes!("fixtures/ed25519_private.pem")).unwrap(),
+		),
+		(
+			Algorithm::HS256,
+			"hmac_sig",
+			EncodingKey::from_secret(b"test secret of hmac signed tokens"),
+		),
+	];
+	let config = config(KeySet::from_file(JWKS_PATH).unwrap().into());
+
+	for (alg, kid, key) in keys {
+		let jwt = signed(alg, Some(kid), &key, "https://issuer.test");
+		let decoded: serde_json::Value = decode_jwt_with_validation(&jwt, &config)
+			.unwrap_or_else(|err| panic!("{alg:?}: {err}"));
+		assert_eq!(decoded, claims("https://issuer.test"));
+	}
+}
+
+#[test]
+fn key_without_kid_is_selected_by_alg() {
+	let config = config(KeySet::from_file(JWKS_PATH).unwrap().into());
+	let jwt = signed(Algorithm::RS256, None, &rsa_key(), "https://issuer.test");
+
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(result.is_ok(), "{result:?}");
+}
+
+#[test]
+fn unknown_kid_is_rejected() {
+	let config = config(KeySet::from_file(JWKS_PATH).unwrap().into());
+	let jwt = signed(
+		Algorithm::RS256,
+		Some("unknown"),
+		&rsa_key(),
+		"https://issuer.test",
+	);
+
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(
+		matches!(&result, Err(DecodeError::KeyNotFound { kid: Some(kid), alg: Algorithm::RS256 }) if kid == "unknown"),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn kid_of_key_with_other_alg_is_rejected() {
+	let config = config(KeySet::from_file(JWKS_PATH).unwrap().into());
+	let jwt = signed(
+		Algorithm::RS256,
+		Some("ec_sig"),
+		&rsa_key(),
+		"https://issuer.test",
+	);
+
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(
+		matches!(result, Err(DecodeError::KeyNotFound { .. })),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn ec_key_without_alg_accepts_only_algorithm_of_its_curve() {
+	let key_set = KeySet::from_file(JWKS_PATH).unwrap();
+	let header = |alg| Header {
+		kid: Some("ec_sig".to_owned()),
+		..Header::new(alg)
+	};
+
+	// `ec_sig` key of P-256 curve doesn't declare `alg`
+	assert!(key_set.find(&header(Algorithm::ES256)).is_some());
+	assert!(key_set.find(&header(Algorithm::ES384)).is_none());
+}
+
+#[test]
+fn key_set_is_selected_by_issuer() {
+	let jwks = std::fs::read_to_string(JWKS_PATH).unwrap();
+	let issuers = HashMap::from([
+		(
+			"https://trusted.test".to_owned(),
+			KeySet::from_json_str(&jwks).unwrap(),
+		),
+		(
+			"https://empty.test".to_owned(),
+			KeySet::from_json_str(r#"{"keys": []}"#).unwrap(),
+		),
+	]);
+	let config = config(KeySource::Issuers(issuers));
+
+	let jwt = signed(
+		Algorithm::RS256,
+		Some("rsa_sig_rs256"),
+		&rsa_key(),
+		"https://trusted.test",
+	);
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(result.is_ok(), "{result:?}");
+
+	let jwt = signed(
+		Algorithm::RS256,
+		Some("rsa_sig_rs256"),
+		&rsa_key(),
+		"https://empty.test",
+	);
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(
+		matches!(result, Err(DecodeError::KeyNotFound { .. })),
+		"{result:?}"
+	);
+
+	let jwt = signed(
+		Algorithm::RS256,
+		Some("rsa_sig_rs256"),
+		&rsa_key(),
+		"https://other.test",
+	);
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(
+		matches!(&result, Err(DecodeError::UnknownIssuer(iss)) if iss == "https://other.test"),
+		"{result:?}"
+	);
+}
@@ -0,0 +1,375 @@
+use std::{
+	collections::HashMap,
+	sync::{mpsc, Arc, Mutex},
+	time::Duration,
+};
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use jwt::{
+	decode_jwt_with_validation, Algorithm, DecodeError, HttpClient, HttpError, HttpResponse,
+	IssuerResolver, IssuerResolverConfig, JWTDecoder, JWTValidationConfig, KeySource,
+	SUPPORTED_ALGORITHMS,
+};
+
+const OPENID_CONFIGURATION_URL: &str = "https://issuer.test/.well-known/openid-configuration";
+const JWKS_URL: &str = "https://issuer.test/jwks";
+
+// serves fixture documents by url and counts requests
+#[derive(Clone, Default)]
+struct StubHttp {
+	documents: Arc<Mutex<HashMap<String, String>>>,
+	requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubHttp {
+	fn new(jwks: &str) -> Self {
+		let stub = Self::default();
+		stub.serve(
+			OPENID_CONFIGURATION_URL,
+			include_str!("fixtures/openid-configuration.json"),
+		);
+		stub.serve(JWKS_URL, jwks);
+		stub
+	}
+
+	fn serve(&self, url: &str, body: &str) {
+		self.documents
+			.lock()
+			.unwrap()
+			.insert(url.to_owned(), body.to_owned());
+	}
+
+	fn requests(&self, url: &str) -> usize {
+		self.requests
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|r| *r == url)
+			.count()
+	}
+}
+
+impl HttpClient for StubHttp {
+	fn get_if_none_match(&self, url: &str, _etag: Option<&str>) -> Result<HttpResponse, HttpError> {
+		self.requests.lock().unwrap().push(url.to_owned());
+		let body = self
+			.documents
+			.lock()
+			.unwrap()
+			.get(url)
+			.cloned()
+			.ok_or_else(|| HttpError {
+				url: url.to_owned(),
+				message: "404 Not Found".to_owned(),
+			})?;
+		Ok(HttpResponse::Modified { body, etag: None })
+	}
+}
+
+fn resolver(http: &StubHttp, config: IssuerResolverConfig) -> JWTValidationConfig {
+	let resolver = IssuerResolver::new(Box::new(http.clone()), config);
+	resolver.add_endpoint(OPENID_CONFIGURATION_URL);
+	JWTValidationConfig::new(SUPPORTED_ALGORITHMS.to_vec(), KeySource::Resolver(resolver))
+}
+
+fn rsa_token(iss: &str) -> String {
+	let mut header = Header::new(Algorithm::RS256);
+	header.kid = Some("rsa_sig_rs256".to_owned());
+	let key = EncodingKey::from_rsa_pem(include_bytes!("fixtures/rsa_private.pem")).unwrap();
+	encode(
+		&header,
+		&serde_json::json!({ "iss": iss, "sub": "alice" }),
+		&key,
+	)
+	.unwrap()
+}
+
+#[test]
+fn keys_are_fetched_once_and_cached() {
+	let http = StubHttp::new(include_str!("fixtures/jwks.json"));
+	let config = resolver(&http, IssuerResolverConfig::default());
+
+	for _ in 0..3 {
+		let result = decode_jwt_with_validation::<serde_json::Value>(
+			&rsa_token("https://issuer.test"),
+			&config,
+		);
+		assert!(result.is_ok(), "{result:?}");
+	}
+	assert_eq!(http.requests(OPENID_CONFIGURATION_URL), 1);
+	assert_eq!(http.requests(JWKS_URL), 1);
+}
+
+#[test]
+fn expired_keys_are_fetched_again() {
+	let http = StubHttp::new(include_str!("fixtures/jwks.json"));
+	let config = resolver(
+		&http,
+		IssuerResolverConfig {
+			ttl: Duration::ZERO,
+			..Default::default()
+		},
+	);
+
+	for _ in 0..2 {
+		let result = decode_jwt_with_validation::<serde_json::Value>(
+			&rsa_token("https://issuer.test"),
+			&config,
+		);
+		assert!(result.is_ok(), "{result:?}");
+	}
+	assert_eq!(http.requests(JWKS_URL), 2);
+
+	// stale keys are used when the issuer is not available
+	http.documents.lock().unwrap().remove(JWKS_URL);
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config);
+	assert!(result.is_ok(), "{result:?}");
+}
+
+#[test]
+fn unknown_kid_refreshes_keys() {
+	let http = StubHttp::new(include_str!("fixtures/jwks_ec_only.json"));
+	let config = resolver(
+		&http,
+		IssuerResolverConfig {
+			refresh_cooldown: Duration::ZERO,
+			..Default::default()
+		},
+	);
+
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config);
+	assert!(
+		matches!(result, Err(DecodeError::KeyNotFound { .. })),
+		"{result:?}"
+	);
+
+	// issuer rotated the keys
+	http.serve(JWKS_URL, include_str!("fixtures/jwks.json"));
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config);
+	assert!(result.is_ok(), "{result:?}");
+	assert_eq!(http.requests(JWKS_URL), 2);
+}
+
+#[test]
+fn refresh_on_unknown_kid_respects_cooldown() {
+	let http = StubHttp::new(include_str!("fixtures/jwks_ec_only.json"));
+	let config = resolver(&http, IssuerResolverConfig::default());
+
+	for _ in 0..3 {
+		let result = decode_jwt_with_validation::<serde_json::Value>(
+			&rsa_token("https://issuer.test"),
+			&config,
+		);
+		assert!(
+			matches!(result, Err(DecodeError::KeyNotFound { .. })),
+			"{result:?}"
+		);
+	}
+	assert_eq!(http.requests(JWKS_URL), 1);
+}
+
+#[test]
+fn failed_refresh_is_not_repeated_during_backoff() {
+	let http = StubHttp::new(include_str!("fixtures/jwks_ec_only.json"));
+	let config = resolver(
+		&http,
+		IssuerResolverConfig {
+			refresh_cooldown: Duration::ZERO,
+			..Default::default()
+		},
+	);
+
+	let decode = || {
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config)
+	};
+	let result = decode();
+	assert!(
+		matches!(result, Err(DecodeError::KeyNotFound { .. })),
+		"{result:?}"
+	);
+
+	// refresh by unknown `kid` fails, the cached keys are still used
+	http.documents.lock().unwrap().remove(JWKS_URL);
+	for _ in 0..3 {
+		let result = decode();
+		assert!(
+			matches!(result, Err(DecodeError::KeyNotFound { .. })),
+			"{result:?}"
+		);
+	}
+	assert_eq!(http.requests(JWKS_URL), 2);
+}
+
+#[test]
+fn unavailable_jwks_is_not_fetched_during_backoff() {
+	let http = StubHttp::new(include_str!("fixtures/jwks.json"));
+	http.documents.lock().unwrap().remove(JWKS_URL);
+	let config = resolver(&http, IssuerResolverConfig::default());
+
+	for _ in 0..3 {
+		let result = decode_jwt_with_validation::<serde_json::Value>(
+			&rsa_token("https://issuer.test"),
+			&config,
+		);
+		assert!(
+			matches!(result, Err(DecodeError::IssuerMetadata(_))),
+			"{result:?}"
+		);
+	}
+	assert_eq!(http.requests(JWKS_URL), 1);
+}
+
+#[test]
+fn token_of_unknown_issuer_is_rejected() {
+	let http = StubHttp::new(include_str!("fixtures/jwks.json"));
+	let config = resolver(&http, IssuerResolverConfig::default());
+
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://other.test"), &config);
+	assert!(
+		matches!(&result, Err(DecodeError::UnknownIssuer(iss)) if iss == "https://other.test"),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn unavailable_issuer_is_retried() {
+	let http = StubHttp::default();
+	let config = resolver(
+		&http,
+		IssuerResolverConfig {
+			retry_backoff: Duration::ZERO,
+			..Default::default()
+		},
+	);
+
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config);
+	assert!(
+		matches!(result, Err(DecodeError::UnknownIssuer(_))),
+		"{result:?}"
+	);
+
+	http.serve(
+		OPENID_CONFIGURATION_URL,
+		include_str!("fixtures/openid-configuration.json"),
+	);
+	http.serve(JWKS_URL, include_str!("fixtures/jwks.json"));
+	let result =
+		decode_jwt_with_validation::<serde_json::Value>(&rsa_token("https://issuer.test"), &config);
+	assert!(result.is_ok(), "{result:?}");
+}
+
+#[test]
+fn unavailable_issuer_is_not_fetched_during_backoff() {
+	let http = StubHttp::default();
+	let config = resolver(&http, IssuerResolverConfig::default());
+
+	for iss in [
+		"https://issuer.test",
+		"https://other.test",
+		"https://third.test",
+	] {
+		let result = decode_jwt_with_validation::<serde_json::Value>(&rsa_token(iss), &config);
+		assert!(
+			matches!(result, Err(DecodeError::UnknownIssuer(_))),
+			"{result:?}"
+		);
+	}
+	assert_eq!(http.requests(OPENID_CONFIGURATION_URL), 1);
+}
+
+#[test]
+fn configuration_of_other_issuer_is_rejected() {
+	let http = StubHttp::new(include_str!("fixtures/jwks.json"));
+	let config = resolver(&http, IssuerResolverConfig::default());
+	http.serve(
+		OPENID_CONFIGURATION_URL,
+		&include_str!("fixtures/openid-configuration.json").replace(
+			"\"issuer\": \"https://issuer.test\"",
+			"\"issuer\": \"https://other.test\"",
+		),
+	);
+
+	for iss in ["https://issuer.test", "https://other.test"] {
+		let result = decode_jwt_with_validation::<serde_json::Value>(&rsa_token(iss), &config);
+		assert!(
+			matches!(result, Err(DecodeError::UnknownIssuer(_))),
+			"{result:?}"
+		);
+	}
+}
+
+// blocks requests of `url` until the test releases them
+struct BlockingHttp {
+	stub: StubHttp,
+	url: &'static str,
+	started: Mutex<mpsc::Sender<()>>,
+	release: Mutex<mpsc::Receiver<()>>,
+	timed_out: Arc<Mutex<bool>>,
+}
+
+impl HttpClient for BlockingHttp {
+	fn get_if_none_match(&self, url: &str, etag: Option<&str>) -> Result<HttpResponse, HttpError> {
+		if url == self.url {
+			self.started.lock().unwrap().send(()).unwrap();
+			let released = self
+				.release
+				.lock()
+				.unwrap()
+				.recv_timeout(Duration::from_secs(5));
+			*self.timed_out.lock().unwrap() = released.is_err();
+		}
+		self.stub.get_if_none_match(url, etag)
+	}
+}
+
+#[test]
+fn slow_issuer_does_not_block_other_issuers() {
+	const SLOW_URL: &str = "https://slow.test/.well-known/openid-configuration";
+	let (started_tx, started_rx) = mpsc::channel();
+	let (release_tx, release_rx) = mpsc::channel();
+	let timed_out = Arc::new(Mutex::new(false));
+	let http = BlockingHttp {
+		stub: StubHttp::new(include_str!("fixtures/jwks.json")),
+		url: SLOW_URL,
+		started: Mutex::new(started_tx),
+		release: Mutex::new(release_rx),
+		timed_out: timed_out.clone(),
+	};
+	let resolver = IssuerResolver::new(Box::new(http), IssuerResolverConfig::default());
+	resolver.add_endpoint(OPENID_CONFIGURATION_URL);
+	let decoder = JWTDecoder::new_with_validation(JWTValidationConfig::new(
+		SUPPORTED_ALGORITHMS.to_vec(),
+		KeySource::Resolver(resolver),
+	));
+
+	let decode = |iss| decoder.decode::<serde_json::Value>(&rsa_token(iss));
+	assert!(decode("https://issuer.test").is_ok());
+
+	decoder.issuer_resolver().unwrap().add_endpoint(SLOW_URL);
+	std::thread::scope(|scope| {
+		let slow = scope.spawn(|| decode("https://slow.test"));
+		started_rx.recv().unwrap();
+
+		// discovery of the slow issuer is in progress
+		let result = decode("https://issuer.test");
+		release_tx.send(()).unwrap();
+		assert!(result.is_ok(), "{result:?}");
+		assert!(slow.join().unwrap().is_err());
+	});
+	assert!(!*timed_out.lock().unwrap());
+}
+
+#[test]
+fn decoder_exposes_resolver() {
+	let http = StubHttp::default();
+	let decoder = JWTDecoder::new_with_validation(resolver(&http, IssuerResolverConfig::default()));
+	assert!(decoder.issuer_resolver().is_some());
+	assert!(JWTDecoder::new_without_validation()
+		.issuer_resolver()
+		.is_none());
+}
@@ -0,0 +1,159 @@
+use jsonwebtoken::{encode, EncodingKey, Header};
+use jwt::{decode_jwt_with_validation, Algorithm, DecodeError, DecodingKey, JWTValidationConfig};
+
+const HMAC_SECRET: &[u8] = b"test secret of hmac signed tokens";
+
+struct KeyPair {
+	alg: Algorithm,
+	encoding: EncodingKey,
+	decoding: DecodingKey,
+}
+
+fn key_pairs() -> Vec<KeyPair> {
+	vec![
+		KeyPair {
+			alg: Algorithm::RS256,
+			encoding: EncodingKey::from_rsa_pem(include_bytes!("fixtures/rsa_private.pem"))
+				.unwrap(),
+			decoding: DecodingKey::from_rsa_pem(include_bytes!("fixtures/rsa_public.pem")).unwrap(),
+		},
+		KeyPair {
+			alg: Algorithm::ES256,
+			encoding: EncodingKey::from_ec_pem(include_bytes!("fixtures/ec_private.pem")).unwrap(),
+			decoding: DecodingKey::from_ec_pem(include_bytes!("fixtures/ec_public.pem")).unwrap(),
+		},
+		KeyPair {
+			alg: Algorithm::EdDSA,
+			encoding: EncodingKey::from_ed_pem(include_bytes!("fixtures/ed25519_private.pem"))
+				.unwrap(),
+			decoding: DecodingKey::from_ed_pem(include_bytes!("fixtures/ed25519_public.pem"))
+				.unwrap(),
+		},
+		KeyPair {
+			alg: Algorithm::HS256,
+			encoding: EncodingKey::from_secret(HMAC_SECRET),
+			decoding: DecodingKey::from_secret(HMAC_SECRET),
+		},
+	]
+}
+
+fn claims() -> serde_json::Value {
+	serde_json::json!({ "iss": "https://issuer.test", "sub": "alice" })
+}
+
+fn config(alg: Algorithm, key: DecodingKey) -> JWTValidationConfig {
+	JWTValidationConfig::new(vec![alg], key)
+}
+
+// replace payload keeping header and signature of the original token
+fn tamper(jwt: &str) -> String {
+	let parts: Vec<&str> = jwt.split('.').collect();
+	let payload =
+		base64_url(&serde_json::json!({ "iss": "https://issuer.test", "sub": "mallory" }));
+	format!("{}.{payload}.{}", parts[0], parts[2])
+}
+
+fn base64_url(value: &serde_json::Value) -> String {
+	use base64::prelude::*;
+	BASE64_URL_SAFE_NO_PAD.encode(value.to_string())
+}
+
+#[test]
+fn valid_signature_is_accepted() {
+	for pair in key_pairs() {
+		let jwt = encode(&Header::new(pair.alg), &claims(), &pair.encoding).unwrap();
+		let decoded: serde_json::Value =
+			decode_jwt_with_validation(&jwt, &config(pair.alg, pair.decoding))
+				.unwrap_or_else(|err| panic!("{:?}: {err}", pair.alg));
+		assert_eq!(decoded, claims(), "{:?}", pair.alg);
+	}
+}
+
+#[test]
+fn tampered_token_is_rejected() {
+	for pair in key_pairs() {
+		let jwt = encode(&Header::new(pair.alg), &claims(), &pair.encoding).unwrap();
+		let result = decode_jwt_with_validation::<serde_json::Value>(
+			&tamper(&jwt),
+			&config(pair.alg, pair.decoding),
+		);
+		assert!(
+			matches!(result, Err(DecodeError::InvalidSignature)),
+			"{:?}: {result:?}",
+			pair.alg
+		);
+	}
+}
+
+#[test]
+fn token_signed_by_other_key_is_rejected() {
+	let other = EncodingKey::from_secret(b"other secret");
+	let jwt = encode(&Header::new(Algorithm::HS256), &claims(), &other).unwrap();
+	let result = decode_jwt_with_validation::<serde_json::Value>(
+		&jwt,
+		&config(Algorithm::HS256, DecodingKey::from_secret(HMAC_SECRET)),
+	);
+	assert!(
+		matches!(result, Err(DecodeError::InvalidSignature)),
+		"{result:?}"
+	);
+}
+
+#[test]
+fn algorithm_that_is_not_accepted_is_rejected() {
+	for pair in key_pairs() {
+		let jwt = encode(&Header::new(pair.alg), &claims(), &pair.encoding).unwrap();
+		let accepted = if pair.alg == Algorithm::HS256 {
+			Algorithm::RS256
+		} else {
+			Algorithm::HS256
+		};
+		let result =
+			decode_jwt_with_validation::<serde_json::Value>(&jwt, &config(accepted, pair.decoding));
+		assert!(
+			matches!(result, Err(DecodeError::UnsupportedAlgorithm(alg)) if alg == pair.alg),
+			"{:?}: {result:?}",
+			pair.alg
+		);
+	}
+}
+
+#[test]
+fn key_of_other_algorithm_is_rejected() {
+	// every token is checked with the key of the next algorithm
+	let pairs = key_pairs();
+	for (i, pair) in pairs.iter().enumerate() {
+		let other = &pairs[(i + 1) % pairs.len()];
+		let jwt = encode(&Header::new(pair.alg), &claims(), &pair.encoding).unwrap();
+		let mut config = config(pair.alg, other.decoding.clone());
+		config.algorithms.push(other.alg);
+
+		let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+		assert!(
+			matches!(result, Err(DecodeError::KeyMismatch(_))),
+			"{:?}: {result:?}",
+			pair.alg
+		);
+	}
+}
+
+#[test]
+fn unsupported_algorithm_is_rejected() {
+	let jwt = encode(
+		&Header::new(Algorithm::HS512),
+		&claims(),
+		&EncodingKey::from_secret(HMAC_SECRET),
+	)
+	.unwrap();
+	let mut config = config(Algorithm::HS256, DecodingKey::from_secret(HMAC_SECRET));
+	config.algorithms.push(Algorithm::HS512);
+
+	let result = decode_jwt_with_validation::<serde_json::Value>(&jwt, &config);
+	assert!(
+		matches!(
+			result,
+			Err(DecodeError::UnsupportedAlgorithm(Algorithm::HS512))
+		),
+		"{result:?}"
+	);
+}
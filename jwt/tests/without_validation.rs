@@ -0,0 +1,14 @@
+use base64::prelude::*;
+use jwt::decode_jwt_without_validation;
+
+// JWT payload is base64url encoded, `-` and `_` are not in the standard alphabet
+#[test]
+fn payload_is_decoded_as_base64url() {
+	let claims = serde_json::json!({ "sub": "???>>>" });
+	let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+	assert!(payload.contains(['-', '_']), "{payload}");
+
+	let jwt = format!("eyJhbGciOiJub25lIn0.{payload}.");
+	let decoded: serde_json::Value = decode_jwt_without_validation(&jwt).unwrap();
+	assert_eq!(decoded, claims);
+}